@@ -3,6 +3,47 @@ use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
 use rand::rngs::OsRng;
 use std::io::{self, Write};
 
+// Must match `hardware_token::APPLICATION_PARAMETER` in the app so a
+// registration minted here authenticates against the same credential there.
+const TOKEN_APPLICATION_PARAMETER: &[u8; 32] = b"pyauto-license-binding-v10000000";
+
+// Mirrors the envelope layout in `src/engine/license.rs`. Kept standalone
+// (rather than importing the tauri lib crate) so the admin console stays a
+// lightweight tool that doesn't pull in the Windows OCR/capture stack.
+const ENVELOPE_VERSION_WITH_TOKEN: u8 = 2;
+
+fn build_envelope(
+    hwid: &str,
+    valid_days: i64,
+    tier: u8,
+    token: Option<(&[u8], &[u8])>,
+) -> Vec<u8> {
+    let issued_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let expires_at = issued_at + valid_days * 86_400;
+
+    let mut payload = Vec::with_capacity(1 + 8 + 8 + 1 + 1 + hwid.len() + 1 + 32);
+    payload.push(ENVELOPE_VERSION_WITH_TOKEN);
+    payload.extend_from_slice(&issued_at.to_le_bytes());
+    payload.extend_from_slice(&expires_at.to_le_bytes());
+    payload.push(tier);
+    payload.push(hwid.len() as u8);
+    payload.extend_from_slice(hwid.as_bytes());
+    match token {
+        Some((key_handle, pubkey)) => {
+            payload.push(1);
+            payload.push(key_handle.len() as u8);
+            payload.extend_from_slice(key_handle);
+            payload.push(pubkey.len() as u8);
+            payload.extend_from_slice(pubkey);
+        }
+        None => payload.push(0),
+    }
+    payload
+}
+
 fn main() {
     loop {
         println!("========================================");
@@ -10,6 +51,7 @@ fn main() {
         println!("   [1] Generate NEW Master Keypair      ");
         println!("   [2] Sign User HWID (Create License)  ");
         println!("   [3] Exit                             ");
+        println!("   [4] Bind License to Hardware Token    ");
         println!("========================================");
         print!("> Select Option: ");
         io::stdout().flush().unwrap();
@@ -21,6 +63,7 @@ fn main() {
             "1" => generate_keys(),
             "2" => sign_hwid(),
             "3" => break,
+            "4" => sign_hwid_with_token(),
             _ => println!("Invalid option."),
         }
 
@@ -53,25 +96,16 @@ fn generate_keys() {
     println!("--------------------------------------------------");
 }
 
-fn sign_hwid() {
-    println!("\n[LICENSE GENERATION]");
-
+fn load_master_signing_key() -> Option<SigningKey> {
     // Hardcoded Master Key (As requested)
     let priv_b64 = "epm7+hYKHoSdQMsydFPoxmeo5ybk1rjH8WUWzh/ug/0=";
     println!("> Using Hardcoded Master Private Key");
 
-    print!("> Enter User HWID: ");
-    io::stdout().flush().unwrap();
-    let mut hwid_in = String::new();
-    io::stdin().read_line(&mut hwid_in).unwrap();
-    let hwid = hwid_in.trim();
-
-    // Decode Private Key
     let priv_bytes = match BASE64.decode(priv_b64) {
         Ok(b) => b,
         Err(e) => {
             println!("Error decoding Private Key: {}", e);
-            return;
+            return None;
         }
     };
 
@@ -79,18 +113,117 @@ fn sign_hwid() {
         Ok(b) => b,
         Err(_) => {
             println!("Invalid Private Key Length (Must be 32 bytes dec)");
-            return;
+            return None;
         }
     };
-    let signing_key = SigningKey::from_bytes(&bytes);
+    Some(SigningKey::from_bytes(&bytes))
+}
+
+fn prompt_hwid_and_terms() -> (String, i64, u8) {
+    print!("> Enter User HWID: ");
+    io::stdout().flush().unwrap();
+    let mut hwid_in = String::new();
+    io::stdin().read_line(&mut hwid_in).unwrap();
+    let hwid = hwid_in.trim().to_string();
 
-    // Sign
-    let signature = signing_key.sign(hwid.as_bytes());
-    let sig_b64 = BASE64.encode(signature.to_bytes());
+    print!("> Validity period in days (e.g. 30, 365): ");
+    io::stdout().flush().unwrap();
+    let mut days_in = String::new();
+    io::stdin().read_line(&mut days_in).unwrap();
+    let valid_days: i64 = days_in.trim().parse().unwrap_or(30);
+
+    println!("> Tier [0=Trial, 1=Standard, 2=Pro, 3=Lifetime]: ");
+    io::stdout().flush().unwrap();
+    let mut tier_in = String::new();
+    io::stdin().read_line(&mut tier_in).unwrap();
+    let tier: u8 = tier_in.trim().parse().unwrap_or(1);
+
+    (hwid, valid_days, tier)
+}
+
+fn sign_and_print(signing_key: &SigningKey, payload: Vec<u8>, hwid: &str, valid_days: i64, tier: u8) {
+    let signature = signing_key.sign(&payload);
+    let mut combined = payload;
+    combined.extend_from_slice(&signature.to_bytes());
+    let code_b64 = BASE64.encode(combined);
 
     println!("\n[LICENSE GENERATED SUCCESSFULLY]");
     println!("--------------------------------------------------");
-    println!("{}", sig_b64);
+    println!("{}", code_b64);
     println!("--------------------------------------------------");
+    println!(
+        "Tier: {}  Valid: {} day(s)  HWID: {}",
+        tier, valid_days, hwid
+    );
     println!("Send this code to the user.");
 }
+
+fn sign_hwid() {
+    println!("\n[LICENSE GENERATION]");
+
+    let Some(signing_key) = load_master_signing_key() else {
+        return;
+    };
+    let (hwid, valid_days, tier) = prompt_hwid_and_terms();
+
+    let payload = build_envelope(&hwid, valid_days, tier, None);
+    sign_and_print(&signing_key, payload, &hwid, valid_days, tier);
+}
+
+fn sign_hwid_with_token() {
+    println!("\n[LICENSE GENERATION - HARDWARE TOKEN BINDING]");
+    println!("> Plug in the user's U2F/FIDO security key and press ENTER to register it.");
+    let mut pause = String::new();
+    io::stdin().read_line(&mut pause).unwrap();
+
+    let devices = match u2fhid::discover_devices() {
+        Ok(d) => d,
+        Err(e) => {
+            println!("U2F device enumeration failed: {e}");
+            return;
+        }
+    };
+    let Some(mut device) = devices.into_iter().next() else {
+        println!("No U2F/FIDO security key detected.");
+        return;
+    };
+
+    let mut challenge = [0u8; 32];
+    {
+        use rand::RngCore;
+        OsRng.fill_bytes(&mut challenge);
+    }
+
+    let registration = match device.register(
+        &challenge,
+        TOKEN_APPLICATION_PARAMETER,
+        u2fhid::RegisterFlags::default(),
+    ) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("Token registration failed: {e}");
+            return;
+        }
+    };
+    println!(
+        "> Registered token. Attestation public key: {}",
+        BASE64.encode(&registration.public_key)
+    );
+
+    let Some(signing_key) = load_master_signing_key() else {
+        return;
+    };
+    let (hwid, valid_days, tier) = prompt_hwid_and_terms();
+
+    // Both the key handle and the attestation pubkey are embedded: the app
+    // needs the former to address this exact credential when it challenges
+    // the token again during verification, and the latter to check the
+    // response against.
+    let payload = build_envelope(
+        &hwid,
+        valid_days,
+        tier,
+        Some((&registration.key_handle, &registration.public_key)),
+    );
+    sign_and_print(&signing_key, payload, &hwid, valid_days, tier);
+}
@@ -0,0 +1,57 @@
+//! True 1-bit binarized export of the OCR-preprocessed buffer.
+//!
+//! `legacy_preprocess` (and some `preprocess::PreprocessStage` pipelines)
+//! collapse every pixel to one of two tones, but the result was only ever
+//! fed to OCR, never saved — so there was no tiny "what the OCR actually
+//! saw" artifact to check when a read goes wrong. This mirrors oxipng's own
+//! color-type/bit-depth reduction: when a grayscale buffer really does hold
+//! only two distinct values, pack 8 pixels per byte into a bit-depth-1 PNG
+//! instead of re-encoding it as 8-bit grayscale.
+
+/// Packs `gray` (`width`x`height`, one byte per pixel) into PNG's
+/// bit-depth-1 grayscale row format — MSB-first bits, each row padded out
+/// to a whole byte — if and only if it holds exactly two distinct values
+/// (the darker one maps to bit `0`, the lighter to bit `1`). Returns `None`
+/// for anything with more than two tones, since reinterpreting that as
+/// 1-bit would silently throw detail away rather than just repack it.
+pub fn pack_1bit(gray: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+    if gray.len() != (width as usize) * (height as usize) {
+        return None;
+    }
+
+    let mut seen: [Option<u8>; 2] = [None, None];
+    for &px in gray {
+        if seen[0] == Some(px) || seen[1] == Some(px) {
+            continue;
+        }
+        match (seen[0], seen[1]) {
+            (None, _) => seen[0] = Some(px),
+            (Some(_), None) => seen[1] = Some(px),
+            _ => return None, // a third distinct value
+        }
+    }
+    let darker = match seen {
+        [Some(a), Some(b)] => a.min(b),
+        [Some(a), None] => a,
+        [None, _] => return None,
+    };
+    Some(pack_1bit_with(gray, width, height, darker))
+}
+
+fn pack_1bit_with(gray: &[u8], width: u32, height: u32, low: u8) -> Vec<u8> {
+    let width = width as usize;
+    let height = height as usize;
+    let bytes_per_row = width.div_ceil(8);
+    let mut packed = vec![0u8; bytes_per_row * height];
+
+    for y in 0..height {
+        for x in 0..width {
+            if gray[y * width + x] != low {
+                let byte_idx = y * bytes_per_row + x / 8;
+                let bit = 7 - (x % 8);
+                packed[byte_idx] |= 1 << bit;
+            }
+        }
+    }
+    packed
+}
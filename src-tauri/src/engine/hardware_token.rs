@@ -0,0 +1,98 @@
+//! Optional hardware-backed license binding over a U2F/FIDO HID security
+//! key, following the same device-enumeration/register/authenticate shape
+//! as u2f-hid-rs. This is stronger than the software HWID in
+//! `license::get_hardware_id`, which can be cloned between machines: a
+//! token-bound license only unlocks while the registered physical key is
+//! plugged in.
+
+use u2fhid::{AuthenticateFlags, RegisterFlags, U2FDevice};
+
+const APPLICATION_PARAMETER: &[u8; 32] = b"pyauto-license-binding-v10000000";
+
+/// A registered U2F credential: the key handle the token issued during
+/// registration (opaque to us, handed back on every `authenticate` call)
+/// and the attestation public key the admin console signs into the license
+/// envelope.
+#[derive(Clone, Debug)]
+pub struct Registration {
+    pub key_handle: Vec<u8>,
+    pub attestation_pubkey: Vec<u8>,
+}
+
+/// A signed challenge response from the token, proving it holds the private
+/// half of a previously-registered key handle.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    pub signature: Vec<u8>,
+    pub counter: u32,
+}
+
+pub struct HardwareToken {
+    device: U2FDevice,
+}
+
+impl HardwareToken {
+    /// Enumerates connected U2F HID devices and opens the first one found.
+    pub fn discover() -> Result<Self, String> {
+        let device = u2fhid::discover_devices()
+            .map_err(|e| format!("U2F device enumeration failed: {e}"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "No U2F/FIDO security key found".to_string())?;
+
+        Ok(Self { device })
+    }
+
+    /// Registers a new credential on the connected token, returning the key
+    /// handle and attestation public key to embed in a license envelope.
+    pub fn register(&mut self) -> Result<Registration, String> {
+        let challenge = rand_challenge();
+
+        let response = self
+            .device
+            .register(&challenge, APPLICATION_PARAMETER, RegisterFlags::default())
+            .map_err(|e| format!("U2F registration failed: {e}"))?;
+
+        Ok(Registration {
+            key_handle: response.key_handle,
+            attestation_pubkey: response.public_key,
+        })
+    }
+
+    /// Issues an authentication challenge against `key_handle` and returns
+    /// the signature blob if the token approves it (typically a touch/tap).
+    /// Callers should verify the signature against the attestation public
+    /// key stored in the license envelope before unlocking.
+    pub fn authenticate(&mut self, key_handle: &[u8], challenge: &[u8]) -> Result<Assertion, String> {
+        let response = self
+            .device
+            .authenticate(
+                challenge,
+                APPLICATION_PARAMETER,
+                key_handle,
+                AuthenticateFlags::default(),
+            )
+            .map_err(|e| format!("U2F authentication failed: {e}"))?;
+
+        Ok(Assertion {
+            signature: response.signature,
+            counter: response.counter,
+        })
+    }
+
+    /// Mints a fresh random challenge and authenticates against it, proving
+    /// the physical token holding `key_handle` is plugged in and responding
+    /// right now. Used by license verification, which only cares that the
+    /// token answered, not the assertion payload itself.
+    pub fn verify_presence(&mut self, key_handle: &[u8]) -> Result<Assertion, String> {
+        let challenge = rand_challenge();
+        self.authenticate(key_handle, &challenge)
+    }
+}
+
+fn rand_challenge() -> [u8; 32] {
+    use rand::RngCore;
+    let mut challenge = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut challenge);
+    challenge
+}
@@ -0,0 +1,352 @@
+//! Background full-text index of OCR sightings.
+//!
+//! Evidence used to be write-and-forget: a PNG on disk plus a fire-and-forget
+//! GAS POST, with no way to ask "what has this bot actually seen?" after the
+//! fact. This indexes every finding the Brain produces into an on-disk
+//! tantivy index (text, matched price, attribute, timestamp, evidence path,
+//! bounding box) so that history can be queried later, e.g. "every time
+//! 'Excalibur' appeared under 1200 gold".
+//!
+//! Indexing runs on its own thread, off the hot capture/OCR path. The Brain
+//! hands sightings to the indexer through `SightingQueue`, a small bounded
+//! ring buffer that drops the oldest entry under backpressure rather than
+//! blocking — the same shape as the `Arc<RwLock<Option<...>>>` frame handoff
+//! between the Body and Brain threads in `engine::mod`, just queued instead
+//! of single-slot. The writer commits every `COMMIT_BATCH` sightings or
+//! `COMMIT_INTERVAL`, whichever comes first, so a crash loses at most the
+//! last partial batch.
+
+use std::collections::VecDeque;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, RangeQuery};
+use tantivy::schema::{Schema, Value, FAST, STORED, STRING, TEXT};
+use tantivy::{Index, IndexWriter, ReloadPolicy, TantivyDocument};
+
+const QUEUE_CAPACITY: usize = 2048;
+const COMMIT_BATCH: usize = 64;
+const COMMIT_INTERVAL: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const WRITER_HEAP_BYTES: usize = 50_000_000;
+
+/// One OCR finding queued for indexing. `attribute`, `timestamp` and
+/// `evidence_path` are shared across every finding from the same frame; the
+/// bounding box and text are per-finding.
+#[derive(Clone, Debug)]
+pub struct Sighting {
+    pub text: String,
+    pub matched_price: Option<f32>,
+    pub attribute: Option<String>,
+    pub timestamp: i64,
+    pub evidence_path: Option<String>,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+/// A ranked, snippet-highlighted query result.
+#[derive(Serialize, Debug, Clone)]
+pub struct HistoryHit {
+    pub text: String,
+    pub matched_price: Option<f32>,
+    pub attribute: Option<String>,
+    pub timestamp: i64,
+    pub evidence_path: Option<String>,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub snippet: String,
+    pub score: f32,
+}
+
+/// Free-text plus optional numeric/time filters for `HistoryIndex::query`.
+#[derive(Clone, Debug, Default)]
+pub struct HistoryQuery {
+    pub text: String,
+    pub min_price: Option<f32>,
+    pub max_price: Option<f32>,
+    pub since: Option<i64>,
+    pub limit: usize,
+}
+
+struct Fields {
+    text: tantivy::schema::Field,
+    attribute: tantivy::schema::Field,
+    matched_price: tantivy::schema::Field,
+    timestamp: tantivy::schema::Field,
+    evidence_path: tantivy::schema::Field,
+    x: tantivy::schema::Field,
+    y: tantivy::schema::Field,
+    w: tantivy::schema::Field,
+    h: tantivy::schema::Field,
+}
+
+/// Bounded drop-oldest queue handing sightings from the Brain thread to the
+/// indexer thread. The hot path never blocks: a full queue just evicts its
+/// oldest entry and keeps going, trading a bit of old history for never
+/// stalling OCR.
+struct SightingQueue {
+    items: Mutex<VecDeque<Sighting>>,
+}
+
+impl SightingQueue {
+    fn new() -> Self {
+        Self {
+            items: Mutex::new(VecDeque::with_capacity(QUEUE_CAPACITY)),
+        }
+    }
+
+    fn push(&self, sighting: Sighting) {
+        let mut items = self.items.lock();
+        if items.len() >= QUEUE_CAPACITY {
+            items.pop_front();
+        }
+        items.push_back(sighting);
+    }
+
+    fn drain_batch(&self, max: usize) -> Vec<Sighting> {
+        let mut items = self.items.lock();
+        let n = max.min(items.len());
+        items.drain(..n).collect()
+    }
+}
+
+/// Owns the on-disk tantivy index and the bounded queue feeding it. Opened
+/// once for the app's lifetime; `enqueue` is cheap enough to call from the
+/// Brain loop every frame.
+pub struct HistoryIndex {
+    index: Index,
+    writer: Mutex<IndexWriter>,
+    reader: tantivy::IndexReader,
+    fields: Fields,
+    queue: Arc<SightingQueue>,
+    active: AtomicBool,
+}
+
+impl HistoryIndex {
+    /// Opens the index at `index_dir`, creating it (and the directory) if
+    /// it doesn't exist yet.
+    pub fn open(index_dir: &Path) -> tantivy::Result<Arc<Self>> {
+        std::fs::create_dir_all(index_dir)?;
+
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let attribute = schema_builder.add_text_field("attribute", TEXT | STORED);
+        let matched_price = schema_builder.add_f64_field("matched_price", STORED | FAST);
+        let timestamp = schema_builder.add_i64_field("timestamp", STORED | FAST);
+        let evidence_path = schema_builder.add_text_field("evidence_path", STRING | STORED);
+        let x = schema_builder.add_f64_field("x", STORED);
+        let y = schema_builder.add_f64_field("y", STORED);
+        let w = schema_builder.add_f64_field("w", STORED);
+        let h = schema_builder.add_f64_field("h", STORED);
+        let schema = schema_builder.build();
+
+        let dir = tantivy::directory::MmapDirectory::open(index_dir)?;
+        let index = Index::open_or_create(dir, schema)?;
+        let writer = index.writer(WRITER_HEAP_BYTES)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()?;
+
+        Ok(Arc::new(Self {
+            index,
+            writer: Mutex::new(writer),
+            reader,
+            fields: Fields {
+                text,
+                attribute,
+                matched_price,
+                timestamp,
+                evidence_path,
+                x,
+                y,
+                w,
+                h,
+            },
+            queue: Arc::new(SightingQueue::new()),
+            active: AtomicBool::new(true),
+        }))
+    }
+
+    /// Queues a sighting for indexing. Never blocks; drops the oldest queued
+    /// sighting if the queue is full.
+    pub fn enqueue(&self, sighting: Sighting) {
+        self.queue.push(sighting);
+    }
+
+    /// Spawns the background thread that drains the queue into the index.
+    /// Runs for the lifetime of the returned handle's owner; stopped via
+    /// `shutdown`.
+    pub fn spawn_indexer(self: &Arc<Self>) -> thread::JoinHandle<()> {
+        let this = self.clone();
+        thread::spawn(move || {
+            let mut since_commit = Instant::now();
+            let mut pending = 0usize;
+
+            while this.active.load(Ordering::SeqCst) {
+                let batch = this.queue.drain_batch(COMMIT_BATCH);
+                if batch.is_empty() {
+                    thread::sleep(POLL_INTERVAL);
+                } else {
+                    let mut writer = this.writer.lock();
+                    for sighting in &batch {
+                        let mut doc = TantivyDocument::default();
+                        doc.add_text(this.fields.text, &sighting.text);
+                        if let Some(attr) = &sighting.attribute {
+                            doc.add_text(this.fields.attribute, attr);
+                        }
+                        if let Some(price) = sighting.matched_price {
+                            doc.add_f64(this.fields.matched_price, price as f64);
+                        }
+                        doc.add_i64(this.fields.timestamp, sighting.timestamp);
+                        if let Some(path) = &sighting.evidence_path {
+                            doc.add_text(this.fields.evidence_path, path);
+                        }
+                        doc.add_f64(this.fields.x, sighting.x as f64);
+                        doc.add_f64(this.fields.y, sighting.y as f64);
+                        doc.add_f64(this.fields.w, sighting.w as f64);
+                        doc.add_f64(this.fields.h, sighting.h as f64);
+                        let _ = writer.add_document(doc);
+                    }
+                    pending += batch.len();
+                }
+
+                if pending > 0
+                    && (pending >= COMMIT_BATCH || since_commit.elapsed() >= COMMIT_INTERVAL)
+                {
+                    let mut writer = this.writer.lock();
+                    let _ = writer.commit();
+                    pending = 0;
+                    since_commit = Instant::now();
+                }
+            }
+
+            // Final commit so nothing queued before shutdown is lost.
+            let mut writer = this.writer.lock();
+            let _ = writer.commit();
+        })
+    }
+
+    /// Stops the background indexer after its current poll tick.
+    pub fn shutdown(&self) {
+        self.active.store(false, Ordering::SeqCst);
+    }
+
+    /// Runs a ranked full-text query (with optional price/time filters)
+    /// against the index, returning hits with highlighted snippets.
+    pub fn query(&self, query: &HistoryQuery) -> Result<Vec<HistoryHit>, String> {
+        let searcher = self.reader.searcher();
+        let query_parser =
+            QueryParser::for_index(&self.index, vec![self.fields.text, self.fields.attribute]);
+
+        let text_query: Box<dyn Query> = if query.text.trim().is_empty() {
+            Box::new(tantivy::query::AllQuery)
+        } else {
+            query_parser
+                .parse_query(&query.text)
+                .map_err(|e| format!("Bad query: {}", e))?
+        };
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, text_query)];
+
+        if query.min_price.is_some() || query.max_price.is_some() {
+            let lower = query
+                .min_price
+                .map(|v| Bound::Included(v as f64))
+                .unwrap_or(Bound::Unbounded);
+            let upper = query
+                .max_price
+                .map(|v| Bound::Included(v as f64))
+                .unwrap_or(Bound::Unbounded);
+            clauses.push((
+                Occur::Must,
+                Box::new(RangeQuery::new_f64_bounds(
+                    self.fields.matched_price,
+                    lower,
+                    upper,
+                )),
+            ));
+        }
+
+        if let Some(since) = query.since {
+            clauses.push((
+                Occur::Must,
+                Box::new(RangeQuery::new_i64_bounds(
+                    self.fields.timestamp,
+                    Bound::Included(since),
+                    Bound::Unbounded,
+                )),
+            ));
+        }
+
+        let combined = BooleanQuery::new(clauses);
+        let limit = if query.limit == 0 { 50 } else { query.limit };
+
+        let top_docs = searcher
+            .search(&combined, &TopDocs::with_limit(limit))
+            .map_err(|e| format!("Search failed: {}", e))?;
+
+        let snippet_generator =
+            tantivy::SnippetGenerator::create(&searcher, &combined, self.fields.text)
+                .map_err(|e| format!("Snippet setup failed: {}", e))?;
+
+        let mut hits = Vec::with_capacity(top_docs.len());
+        for (score, doc_address) in top_docs {
+            let doc: TantivyDocument = searcher
+                .doc(doc_address)
+                .map_err(|e| format!("Doc fetch failed: {}", e))?;
+
+            let text = field_str(&doc, self.fields.text).unwrap_or_default();
+            let attribute = field_str(&doc, self.fields.attribute);
+            let matched_price = field_f64(&doc, self.fields.matched_price).map(|v| v as f32);
+            let timestamp = field_i64(&doc, self.fields.timestamp).unwrap_or(0);
+            let evidence_path = field_str(&doc, self.fields.evidence_path);
+            let x = field_f64(&doc, self.fields.x).unwrap_or(0.0) as f32;
+            let y = field_f64(&doc, self.fields.y).unwrap_or(0.0) as f32;
+            let w = field_f64(&doc, self.fields.w).unwrap_or(0.0) as f32;
+            let h = field_f64(&doc, self.fields.h).unwrap_or(0.0) as f32;
+            let snippet = snippet_generator.snippet_from_doc(&doc).to_html();
+
+            hits.push(HistoryHit {
+                text,
+                matched_price,
+                attribute,
+                timestamp,
+                evidence_path,
+                x,
+                y,
+                w,
+                h,
+                snippet,
+                score,
+            });
+        }
+
+        Ok(hits)
+    }
+}
+
+fn field_str(doc: &TantivyDocument, field: tantivy::schema::Field) -> Option<String> {
+    doc.get_first(field)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn field_f64(doc: &TantivyDocument, field: tantivy::schema::Field) -> Option<f64> {
+    doc.get_first(field).and_then(|v| v.as_f64())
+}
+
+fn field_i64(doc: &TantivyDocument, field: tantivy::schema::Field) -> Option<i64> {
+    doc.get_first(field).and_then(|v| v.as_i64())
+}
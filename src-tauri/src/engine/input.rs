@@ -5,18 +5,106 @@ use windows::Win32::UI::Input::KeyboardAndMouse::{
     SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYEVENTF_KEYUP,
     MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEINPUT, VIRTUAL_KEY,
 };
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, VkKeyScanW, KEYEVENTF_UNICODE,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    SetCursorPos, SystemParametersInfoW, SPI_GETKEYBOARDDELAY, SPI_GETKEYBOARDSPEED,
+    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+};
+
+/// How often `wait_for_key_release`/`wait_for_key_press` poll `GetAsyncKeyState`.
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Clamp bounds so a pathological calibration read (or a machine with an
+/// unusual driver) can't make input feel robotic (too tight) or laggy (too
+/// loose).
+const HOLD_MS_FLOOR: u64 = 30;
+const HOLD_MS_CEIL: u64 = 220;
+
+/// Min/max hold-time window derived from this machine's configured keyboard
+/// repeat speed and delay, used to scale randomized press/hold timing so it
+/// resembles the operator's actual typing cadence instead of a fixed range.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyTimingProfile {
+    pub hold_min_ms: u64,
+    pub hold_max_ms: u64,
+}
+
+impl Default for KeyTimingProfile {
+    fn default() -> Self {
+        // Matches the previous hardcoded 50-120ms hold window.
+        Self {
+            hold_min_ms: 50,
+            hold_max_ms: 120,
+        }
+    }
+}
+
+impl KeyTimingProfile {
+    /// Reads `SPI_GETKEYBOARDSPEED` (0-31, repeat rate) and
+    /// `SPI_GETKEYBOARDDELAY` (0-3, repeat delay) from the current user's
+    /// keyboard settings and converts them into a millisecond hold window.
+    pub fn from_system_settings() -> Self {
+        let mut speed: u32 = 0;
+        let mut delay: u32 = 0;
+
+        unsafe {
+            let _ = SystemParametersInfoW(
+                SPI_GETKEYBOARDSPEED,
+                0,
+                Some(&mut speed as *mut u32 as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            );
+            let _ = SystemParametersInfoW(
+                SPI_GETKEYBOARDDELAY,
+                0,
+                Some(&mut delay as *mut u32 as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            );
+        }
+
+        // Speed 0..=31 maps roughly to a 2.5cps..=30cps repeat rate; turn
+        // that into the inter-repeat interval in ms, which tracks how
+        // quickly this user's OS expects a keystroke to register.
+        let speed = speed.min(31) as f64;
+        let repeat_cps = 2.5 + (speed / 31.0) * 27.5;
+        let interval_ms = 1000.0 / repeat_cps;
+
+        // Delay 0..=3 maps to ~250ms..=1000ms before auto-repeat kicks in;
+        // a longer configured delay implies a more deliberate, slower press.
+        let delay_ms = (delay.min(3) as f64 + 1.0) * 250.0;
+        let delay_factor = delay_ms / 250.0; // 1.0..=4.0
+
+        let hold_min = (interval_ms * 0.5 * delay_factor.sqrt()) as u64;
+        let hold_max = (interval_ms * 1.5 * delay_factor.sqrt()) as u64;
+
+        Self {
+            hold_min_ms: hold_min.clamp(HOLD_MS_FLOOR, HOLD_MS_CEIL),
+            hold_max_ms: hold_max.clamp(HOLD_MS_FLOOR + 1, HOLD_MS_CEIL + 50).max(hold_min + 1),
+        }
+    }
+}
 
 pub struct InputController {
     rng: rand::rngs::ThreadRng,
+    timing: KeyTimingProfile,
 }
 
 impl InputController {
     pub fn new() -> Self {
         Self {
             rng: rand::thread_rng(),
+            timing: KeyTimingProfile::from_system_settings(),
         }
     }
 
+    /// Lets a caller override the calibrated hold-time window, e.g. to
+    /// restore the old fixed behavior or tune it per-profile.
+    pub fn set_timing_profile(&mut self, timing: KeyTimingProfile) {
+        self.timing = timing;
+    }
+
     pub fn click_mouse_left(&mut self) {
         // Stochastic delay before
         self.random_sleep(20, 50);
@@ -26,12 +114,26 @@ impl InputController {
         self.send_mouse_input(MOUSEEVENTF_LEFTUP);
     }
 
+    /// Moves the cursor to absolute screen coordinates `(x, y)` and performs
+    /// a left click there. Used by scripted rule actions (`click(x, y)`)
+    /// where the script names a specific point rather than clicking wherever
+    /// the cursor already sits.
+    pub fn click_at(&mut self, x: i32, y: i32) {
+        unsafe {
+            let _ = SetCursorPos(x, y);
+        }
+        self.click_mouse_left();
+    }
+
     pub fn press_key(&mut self, vk: VIRTUAL_KEY) {
         // Stochastic delay
         self.random_sleep(20, 50);
 
+        // Avoid stacking a synthetic keydown on a physically-held key.
+        self.wait_for_key_release(vk, Duration::from_millis(500));
+
         self.send_key_input(vk, false); // Press
-        self.random_sleep(50, 120); // Human hold time
+        self.random_sleep(self.timing.hold_min_ms, self.timing.hold_max_ms); // Calibrated hold time
         self.send_key_input(vk, true); // Release
     }
 
@@ -39,13 +141,51 @@ impl InputController {
         self.random_sleep(20, 50);
         self.send_key_input(vk, false); // Down
 
-        // Hold for duration + small jitter
-        let jitter = self.rng.gen_range(0..=100);
+        // Hold for duration + calibrated jitter, same profile `press_key`
+        // uses for its own hold time.
+        let jitter = self
+            .rng
+            .gen_range(self.timing.hold_min_ms..=self.timing.hold_max_ms);
         thread::sleep(Duration::from_millis(duration_ms + jitter));
 
         self.send_key_input(vk, true); // Up
     }
 
+    /// Reads the real, physical state of `vk` via `GetAsyncKeyState` (the
+    /// high-order bit is set when the key is currently down). Use this
+    /// before emitting a synthetic keydown to avoid stacking it on top of a
+    /// physically-held key, which produces stuck-key artifacts.
+    pub fn is_key_down(&self, vk: VIRTUAL_KEY) -> bool {
+        let state = unsafe { GetAsyncKeyState(vk.0 as i32) };
+        (state as u16 & 0x8000) != 0
+    }
+
+    /// Blocks until `vk` is no longer physically held, or `timeout` elapses.
+    /// Returns `true` if the key was released before the timeout.
+    pub fn wait_for_key_release(&self, vk: VIRTUAL_KEY, timeout: Duration) -> bool {
+        let start = std::time::Instant::now();
+        while self.is_key_down(vk) {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(KEY_POLL_INTERVAL);
+        }
+        true
+    }
+
+    /// Blocks until `vk` is physically pressed, or `timeout` elapses.
+    /// Returns `true` if the key was pressed before the timeout.
+    pub fn wait_for_key_press(&self, vk: VIRTUAL_KEY, timeout: Duration) -> bool {
+        let start = std::time::Instant::now();
+        while !self.is_key_down(vk) {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(KEY_POLL_INTERVAL);
+        }
+        true
+    }
+
     fn send_mouse_input(
         &self,
         flags: windows::Win32::UI::Input::KeyboardAndMouse::MOUSE_EVENT_FLAGS,
@@ -59,7 +199,7 @@ impl InputController {
                 },
             },
         };
-        unsafe { SendInput(&[input], std::mem::size_of::<INPUT>() as i32) };
+        self.send_batch(&[input]);
     }
 
     fn send_key_input(&self, vk: VIRTUAL_KEY, key_up: bool) {
@@ -78,7 +218,104 @@ impl InputController {
                 },
             },
         };
-        unsafe { SendInput(&[input], std::mem::size_of::<INPUT>() as i32) };
+        self.send_batch(&[input]);
+    }
+
+    /// Passes a contiguous slice of `INPUT` events to a single `SendInput`
+    /// call, so a multi-event sequence (e.g. a whole typed string) costs one
+    /// syscall instead of one per event.
+    fn send_batch(&self, inputs: &[INPUT]) {
+        if inputs.is_empty() {
+            return;
+        }
+        unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+    }
+
+    fn unicode_key_input(scan_code: u16, key_up: bool) -> INPUT {
+        let mut flags = KEYEVENTF_UNICODE;
+        if key_up {
+            flags |= KEYEVENTF_KEYUP;
+        }
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(0),
+                    wScan: scan_code,
+                    dwFlags: flags,
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    fn vk_key_input(vk: VIRTUAL_KEY, shift: bool, key_up: bool) -> [INPUT; 1] {
+        let flags = if key_up {
+            KEYEVENTF_KEYUP
+        } else {
+            windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS(0)
+        };
+        let _ = shift; // Shift is sent as its own keydown/keyup, see type_string.
+        [INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    dwFlags: flags,
+                    ..Default::default()
+                },
+            },
+        }]
+    }
+
+    /// Types `text` by mapping each character to a keydown/keyup pair:
+    /// layout-dependent keys (letters, digits, punctuation the current
+    /// keyboard layout actually has a key for) go through `VkKeyScanW` so
+    /// modifier state (e.g. Shift for uppercase) is respected, and anything
+    /// else is sent as a Unicode character event via `KEYEVENTF_UNICODE`
+    /// with `wScan` set. All events for one character are flushed through a
+    /// single `send_batch` call, with the existing randomized inter-keystroke
+    /// delay between characters.
+    pub fn type_string(&mut self, text: &str) {
+        for ch in text.chars() {
+            let mut batch: Vec<INPUT> = Vec::with_capacity(4);
+
+            let vk_scan = unsafe { VkKeyScanW(ch as u16) };
+            let layout_has_key = vk_scan != -1;
+
+            if layout_has_key {
+                let vk = VIRTUAL_KEY((vk_scan as u16) & 0xFF);
+                let shift_state = (vk_scan as u16) >> 8;
+                let needs_shift = shift_state & 0x01 != 0;
+
+                if needs_shift {
+                    batch.extend_from_slice(&Self::vk_key_input(
+                        windows::Win32::UI::Input::KeyboardAndMouse::VK_SHIFT,
+                        false,
+                        false,
+                    ));
+                }
+                batch.extend_from_slice(&Self::vk_key_input(vk, needs_shift, false));
+                batch.extend_from_slice(&Self::vk_key_input(vk, needs_shift, true));
+                if needs_shift {
+                    batch.extend_from_slice(&Self::vk_key_input(
+                        windows::Win32::UI::Input::KeyboardAndMouse::VK_SHIFT,
+                        false,
+                        true,
+                    ));
+                }
+            } else {
+                // No virtual key for this character on the active layout
+                // (e.g. most non-Latin text); emit it as a raw Unicode event.
+                let scan_code = ch as u16;
+                batch.push(Self::unicode_key_input(scan_code, false));
+                batch.push(Self::unicode_key_input(scan_code, true));
+            }
+
+            self.send_batch(&batch);
+            let (min, max) = (self.timing.hold_min_ms, self.timing.hold_max_ms);
+            self.random_sleep(min, max);
+        }
     }
 
     fn random_sleep(&mut self, min_ms: u64, max_ms: u64) {
@@ -0,0 +1,396 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use winreg::enums::*;
+use winreg::RegKey;
+
+// Public half of the master keypair held by the admin console. Swap this for
+// the value printed by `admin.exe`'s "Generate NEW Master Keypair" option.
+const EMBEDDED_PUBLIC_KEY_B64: &str = "wdMwAIooKtVhEp1CL+gB22weH3U/W5te9OFlnI9R4FQ=";
+
+const LICENSE_REG_PATH: &str = r"Software\pyauto\License";
+const LICENSE_REG_VALUE: &str = "Signature";
+
+fn embedded_verifying_key() -> Option<VerifyingKey> {
+    let bytes = BASE64.decode(EMBEDDED_PUBLIC_KEY_B64).ok()?;
+    let bytes: [u8; 32] = bytes.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+/// Licensing tiers encoded as the single tier byte in a `LicenseEnvelope`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LicenseTier {
+    Trial = 0,
+    Standard = 1,
+    Pro = 2,
+    Lifetime = 3,
+}
+
+impl LicenseTier {
+    fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            0 => Some(Self::Trial),
+            1 => Some(Self::Standard),
+            2 => Some(Self::Pro),
+            3 => Some(Self::Lifetime),
+            _ => None,
+        }
+    }
+}
+
+const ENVELOPE_VERSION_HWID_ONLY: u8 = 1;
+const ENVELOPE_VERSION_WITH_TOKEN: u8 = 2;
+
+/// The signed payload inside an activation code: a version byte, issue/expiry
+/// Unix timestamps, a tier byte, the HWID the license is bound to, and
+/// (version 2+) an optional U2F/FIDO hardware token attestation public key.
+/// The whole serialized payload is ed25519-signed; the activation code is
+/// `base64(payload || signature)`.
+#[derive(Clone, Debug)]
+pub struct LicenseEnvelope {
+    pub issued_at: i64,
+    pub expires_at: i64,
+    pub tier: LicenseTier,
+    pub hwid: String,
+    /// When present, the license is also (or instead of HWID, at the
+    /// caller's discretion) bound to this hardware token; see
+    /// `hardware_token::HardwareToken`.
+    pub token_pubkey: Option<Vec<u8>>,
+    /// The U2F key handle minted alongside `token_pubkey` at registration
+    /// time, needed to address the right credential when challenging the
+    /// token during verification. Always `Some` exactly when `token_pubkey`
+    /// is.
+    pub token_key_handle: Option<Vec<u8>>,
+}
+
+impl LicenseEnvelope {
+    fn serialize_payload(&self) -> Vec<u8> {
+        let hwid_bytes = self.hwid.as_bytes();
+        let mut payload = Vec::with_capacity(1 + 8 + 8 + 1 + 1 + hwid_bytes.len() + 1 + 32);
+        payload.push(ENVELOPE_VERSION_WITH_TOKEN);
+        payload.extend_from_slice(&self.issued_at.to_le_bytes());
+        payload.extend_from_slice(&self.expires_at.to_le_bytes());
+        payload.push(self.tier as u8);
+        payload.push(hwid_bytes.len() as u8);
+        payload.extend_from_slice(hwid_bytes);
+        match (&self.token_key_handle, &self.token_pubkey) {
+            (Some(key_handle), Some(pubkey)) => {
+                payload.push(1);
+                payload.push(key_handle.len() as u8);
+                payload.extend_from_slice(key_handle);
+                payload.push(pubkey.len() as u8);
+                payload.extend_from_slice(pubkey);
+            }
+            _ => payload.push(0),
+        }
+        payload
+    }
+
+    fn parse_payload(payload: &[u8]) -> Option<Self> {
+        const FIXED_HEADER_LEN: usize = 1 + 8 + 8 + 1;
+        if payload.len() < FIXED_HEADER_LEN {
+            return None;
+        }
+        let issued_at = i64::from_le_bytes(payload[1..9].try_into().ok()?);
+        let expires_at = i64::from_le_bytes(payload[9..17].try_into().ok()?);
+        let tier = LicenseTier::from_byte(payload[17])?;
+
+        match payload[0] {
+            ENVELOPE_VERSION_HWID_ONLY => {
+                let hwid = String::from_utf8(payload[FIXED_HEADER_LEN..].to_vec()).ok()?;
+                Some(Self {
+                    issued_at,
+                    expires_at,
+                    tier,
+                    hwid,
+                    token_pubkey: None,
+                    token_key_handle: None,
+                })
+            }
+            ENVELOPE_VERSION_WITH_TOKEN => {
+                let mut cursor = FIXED_HEADER_LEN;
+                let hwid_len = *payload.get(cursor)? as usize;
+                cursor += 1;
+                let hwid = String::from_utf8(payload.get(cursor..cursor + hwid_len)?.to_vec()).ok()?;
+                cursor += hwid_len;
+
+                let has_token = *payload.get(cursor)?;
+                cursor += 1;
+                let (token_key_handle, token_pubkey) = if has_token == 1 {
+                    let handle_len = *payload.get(cursor)? as usize;
+                    cursor += 1;
+                    let key_handle = payload.get(cursor..cursor + handle_len)?.to_vec();
+                    cursor += handle_len;
+
+                    let pubkey_len = *payload.get(cursor)? as usize;
+                    cursor += 1;
+                    let pubkey = payload.get(cursor..cursor + pubkey_len)?.to_vec();
+
+                    (Some(key_handle), Some(pubkey))
+                } else {
+                    (None, None)
+                };
+
+                Some(Self {
+                    issued_at,
+                    expires_at,
+                    tier,
+                    hwid,
+                    token_pubkey,
+                    token_key_handle,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Signs this envelope with the master signing key and returns the
+    /// activation code as `base64(payload || signature)`.
+    pub fn sign(&self, signing_key: &SigningKey) -> String {
+        let payload = self.serialize_payload();
+        let signature = signing_key.sign(&payload);
+        let mut combined = payload;
+        combined.extend_from_slice(&signature.to_bytes());
+        BASE64.encode(combined)
+    }
+
+    /// Decodes an activation code, verifies its signature against the
+    /// embedded master public key, and returns the envelope if valid.
+    /// Does not check expiry or HWID binding; callers do that separately
+    /// so they can report why a code was rejected.
+    pub fn decode_and_verify(activation_code: &str) -> Option<Self> {
+        let verifying_key = embedded_verifying_key()?;
+        let combined = BASE64.decode(activation_code.trim()).ok()?;
+        if combined.len() < 64 {
+            return None;
+        }
+        let (payload, sig_bytes) = combined.split_at(combined.len() - 64);
+        let sig_bytes: [u8; 64] = sig_bytes.try_into().ok()?;
+        let signature = Signature::from_bytes(&sig_bytes);
+        verifying_key.verify(payload, &signature).ok()?;
+        Self::parse_payload(payload)
+    }
+
+    /// True if the envelope has not yet expired.
+    pub fn is_within_validity(&self) -> bool {
+        let now = chrono::Utc::now().timestamp();
+        now <= self.expires_at
+    }
+
+    /// True if this envelope is bound to `hwid`, or (when it carries a
+    /// hardware-token attestation key) to `token_pubkey`. A token-bound
+    /// envelope is checked against the token first since that's the
+    /// stronger guarantee; callers that only have a HWID can pass `None`.
+    pub fn is_bound_to(&self, hwid: &str, token_pubkey: Option<&[u8]>) -> bool {
+        match (&self.token_pubkey, token_pubkey) {
+            (Some(expected), Some(actual)) => expected.as_slice() == actual,
+            (Some(_), None) => false,
+            (None, _) => self.hwid == hwid,
+        }
+    }
+}
+
+/// Derives a stable per-machine identifier from the Windows Cryptography
+/// MachineGuid and the system drive's volume serial, hashed together so the
+/// raw registry value is never exposed directly.
+pub fn get_hardware_id() -> String {
+    let machine_guid = read_machine_guid().unwrap_or_else(|| "unknown-machine-guid".to_string());
+    let volume_serial = read_volume_serial().unwrap_or(0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(machine_guid.as_bytes());
+    hasher.update(volume_serial.to_le_bytes());
+    let digest = hasher.finalize();
+
+    hex::encode(digest)
+}
+
+fn read_machine_guid() -> Option<String> {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let key = hklm
+        .open_subkey(r"SOFTWARE\Microsoft\Cryptography")
+        .ok()?;
+    key.get_value("MachineGuid").ok()
+}
+
+fn read_volume_serial() -> Option<u32> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetVolumeInformationW;
+
+    let root = "C:\\\0".encode_utf16().collect::<Vec<u16>>();
+    let mut serial: u32 = 0;
+
+    unsafe {
+        GetVolumeInformationW(
+            PCWSTR(root.as_ptr()),
+            None,
+            Some(&mut serial),
+            None,
+            None,
+            None,
+        )
+        .ok()?;
+    }
+
+    Some(serial)
+}
+
+/// Verifies an activation code's signature and checks that it has not
+/// expired and is bound to this machine. For a HWID-only envelope that's
+/// just a string comparison; for a hardware-token-bound envelope (`token_pubkey`
+/// and `token_key_handle` both set) it also requires the registered U2F/FIDO
+/// key to be plugged in and answer a fresh challenge before the attestation
+/// is accepted.
+pub fn verify_signature(hwid: &str, activation_code: &str) -> bool {
+    match LicenseEnvelope::decode_and_verify(activation_code) {
+        Some(envelope) => envelope.is_within_validity() && check_binding(&envelope, hwid),
+        None => false,
+    }
+}
+
+/// Checks HWID (or hardware-token) binding for a decoded, signature-valid
+/// envelope.
+fn check_binding(envelope: &LicenseEnvelope, hwid: &str) -> bool {
+    match (&envelope.token_pubkey, &envelope.token_key_handle) {
+        (Some(expected_pubkey), Some(key_handle)) => {
+            let Ok(mut token) = super::hardware_token::HardwareToken::discover() else {
+                return false;
+            };
+            if token.verify_presence(key_handle).is_err() {
+                return false;
+            }
+            envelope.is_bound_to(hwid, Some(expected_pubkey))
+        }
+        _ => envelope.is_bound_to(hwid, None),
+    }
+}
+
+/// Generates a fresh master keypair for the admin console, returned as
+/// (private_b64, public_b64).
+pub fn data_generate_admin_keys() -> (String, String) {
+    use rand::RngCore;
+    let mut csprng = OsRng;
+    let mut bytes = [0u8; 32];
+    csprng.fill_bytes(&mut bytes);
+
+    let signing_key = SigningKey::from_bytes(&bytes);
+    let verifying_key = VerifyingKey::from(&signing_key);
+
+    (
+        BASE64.encode(signing_key.to_bytes()),
+        BASE64.encode(verifying_key.to_bytes()),
+    )
+}
+
+/// Persists and reloads an activated license signature in the current
+/// user's registry hive so the app can silently re-validate on startup
+/// instead of prompting for the signature every launch.
+pub struct LicenseStore;
+
+impl LicenseStore {
+    /// Writes `signature_b64` under `HKCU\Software\pyauto\License` inside a
+    /// registry transaction: open the transaction, create the subkey inside
+    /// it, set the value, commit. If the process dies mid-write the
+    /// transaction rolls back instead of leaving a half-written key.
+    pub fn save_signature(signature_b64: &str) -> Result<(), String> {
+        use windows::core::PCWSTR;
+        use windows::Win32::Foundation::CloseHandle;
+        use windows::Win32::Storage::FileSystem::CreateTransaction;
+        use windows::Win32::System::Registry::{
+            RegCloseKey, RegCreateKeyTransactedW, RegSetValueExW, HKEY_CURRENT_USER, KEY_WRITE,
+            REG_OPTION_NON_VOLATILE, REG_SZ,
+        };
+
+        unsafe {
+            let transaction = CreateTransaction(
+                None,
+                std::ptr::null_mut(),
+                0,
+                0,
+                0,
+                0,
+                None,
+            )
+            .map_err(|e| format!("CreateTransaction failed: {e}"))?;
+
+            let subkey: Vec<u16> = LICENSE_REG_PATH
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .collect();
+            let mut hkey = Default::default();
+
+            let status = RegCreateKeyTransactedW(
+                HKEY_CURRENT_USER,
+                PCWSTR(subkey.as_ptr()),
+                0,
+                None,
+                REG_OPTION_NON_VOLATILE,
+                KEY_WRITE,
+                None,
+                &mut hkey,
+                None,
+                transaction,
+                None,
+            );
+            if status.is_err() {
+                let _ = CloseHandle(transaction);
+                return Err(format!("RegCreateKeyTransactedW failed: {status:?}"));
+            }
+
+            let value_name: Vec<u16> = LICENSE_REG_VALUE
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .collect();
+            let value_data: Vec<u16> = signature_b64
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .collect();
+            let value_bytes = std::slice::from_raw_parts(
+                value_data.as_ptr() as *const u8,
+                value_data.len() * 2,
+            );
+
+            let status =
+                RegSetValueExW(hkey, PCWSTR(value_name.as_ptr()), 0, REG_SZ, Some(value_bytes));
+            let _ = RegCloseKey(hkey);
+
+            if status.is_err() {
+                let _ = CloseHandle(transaction);
+                return Err(format!("RegSetValueExW failed: {status:?}"));
+            }
+
+            windows::Win32::Storage::FileSystem::CommitTransaction(transaction)
+                .map_err(|e| format!("CommitTransaction failed: {e}"))?;
+            let _ = CloseHandle(transaction);
+        }
+
+        Ok(())
+    }
+
+    /// Reads back the previously activated signature, if any.
+    pub fn load_signature() -> Option<String> {
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let key = hkcu.open_subkey(LICENSE_REG_PATH).ok()?;
+        key.get_value(LICENSE_REG_VALUE).ok()
+    }
+
+    /// Re-derives the HWID and checks the stored signature against it
+    /// without any console interaction. Returns `true` if the app is
+    /// already activated on this machine.
+    pub fn is_activated() -> bool {
+        match Self::load_signature() {
+            Some(sig) => verify_signature(&get_hardware_id(), &sig),
+            None => false,
+        }
+    }
+
+    /// Activates the app by persisting `signature_b64`, but only if it
+    /// verifies against the current HWID first.
+    pub fn activate(signature_b64: &str) -> Result<(), String> {
+        if !verify_signature(&get_hardware_id(), signature_b64) {
+            return Err("Signature does not match this machine's HWID".to_string());
+        }
+        Self::save_signature(signature_b64)
+    }
+}
@@ -0,0 +1,333 @@
+//! Observability for the capture -> OCR -> rule -> action pipeline.
+//!
+//! Previously the only visibility into throughput, or into why a rule keeps
+//! not firing, was the ad-hoc `emit_log` stream. This tracks the same
+//! pipeline with atomic counters and histograms (frames captured, OCR
+//! latency, findings per frame, per-rule eval/match/cooldown-suppression
+//! counts, GAS/Discord success/failure), updated from the Brain thread with
+//! no locking on the hot path. It's exposed two ways: `Metrics::snapshot`
+//! for the Tauri dashboard command, and an optional Prometheus text
+//! endpoint (`serve_prometheus`) bound to a configurable port for scraping.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+
+/// Cumulative (Prometheus `le`-style) histogram: each bucket counts every
+/// observation less-than-or-equal-to its bound, plus an implicit `+Inf`
+/// bucket holding the total count.
+struct Histogram {
+    bounds: &'static [f64],
+    buckets: Vec<AtomicU64>,
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one observation. `value` and the running sum are both
+    /// scaled by 1000 and truncated to an integer before storing, since
+    /// there's no atomic f64 — callers get millis-of-precision back out.
+    fn record(&self, value: f64) {
+        for (bucket, &bound) in self.buckets.iter().zip(self.bounds.iter()) {
+            if value <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum.fetch_add((value * 1000.0).max(0.0) as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .bounds
+                .iter()
+                .zip(self.buckets.iter())
+                .map(|(&bound, count)| (bound, count.load(Ordering::Relaxed)))
+                .collect(),
+            sum: self.sum.load(Ordering::Relaxed) as f64 / 1000.0,
+            count: self.count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HistogramSnapshot {
+    /// `(upper_bound, cumulative_count)` pairs, ascending.
+    pub buckets: Vec<(f64, u64)>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+/// OCR latency buckets, in milliseconds.
+const OCR_LATENCY_BOUNDS: &[f64] = &[5.0, 10.0, 25.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0];
+/// Findings-per-frame buckets (a count, not a duration, but the same
+/// cumulative-histogram shape is the natural fit).
+const FINDINGS_BOUNDS: &[f64] = &[0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0];
+
+#[derive(Default)]
+struct RuleStats {
+    evaluations: AtomicU64,
+    matches: AtomicU64,
+    cooldown_suppressions: AtomicU64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RuleStatsSnapshot {
+    pub rule_id: String,
+    pub evaluations: u64,
+    pub matches: u64,
+    pub cooldown_suppressions: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MetricsSnapshot {
+    pub frames_captured: u64,
+    pub ocr_runs: u64,
+    pub ocr_latency_ms: HistogramSnapshot,
+    pub findings_per_frame: HistogramSnapshot,
+    pub rules: Vec<RuleStatsSnapshot>,
+    pub gas_success: u64,
+    pub gas_failure: u64,
+    pub discord_success: u64,
+    pub discord_failure: u64,
+}
+
+/// Counters and histograms for one run of the engine. Opened once per
+/// `RustBot` (like `HistoryIndex`) so a snapshot is available whether or
+/// not the engine is currently active.
+pub struct Metrics {
+    frames_captured: AtomicU64,
+    ocr_runs: AtomicU64,
+    ocr_latency_ms: Histogram,
+    findings_per_frame: Histogram,
+    rules: RwLock<HashMap<String, Arc<RuleStats>>>,
+    gas_success: AtomicU64,
+    gas_failure: AtomicU64,
+    discord_success: AtomicU64,
+    discord_failure: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            frames_captured: AtomicU64::new(0),
+            ocr_runs: AtomicU64::new(0),
+            ocr_latency_ms: Histogram::new(OCR_LATENCY_BOUNDS),
+            findings_per_frame: Histogram::new(FINDINGS_BOUNDS),
+            rules: RwLock::new(HashMap::new()),
+            gas_success: AtomicU64::new(0),
+            gas_failure: AtomicU64::new(0),
+            discord_success: AtomicU64::new(0),
+            discord_failure: AtomicU64::new(0),
+        })
+    }
+
+    pub fn record_frame_captured(&self) {
+        self.frames_captured.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_ocr(&self, latency_ms: f64, finding_count: usize) {
+        self.ocr_runs.fetch_add(1, Ordering::Relaxed);
+        self.ocr_latency_ms.record(latency_ms);
+        self.findings_per_frame.record(finding_count as f64);
+    }
+
+    fn rule_stats(&self, rule_id: &str) -> Arc<RuleStats> {
+        if let Some(stats) = self.rules.read().get(rule_id) {
+            return stats.clone();
+        }
+        self.rules
+            .write()
+            .entry(rule_id.to_string())
+            .or_insert_with(|| Arc::new(RuleStats::default()))
+            .clone()
+    }
+
+    pub fn record_rule_eval(&self, rule_id: &str) {
+        self.rule_stats(rule_id)
+            .evaluations
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rule_match(&self, rule_id: &str) {
+        self.rule_stats(rule_id)
+            .matches
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rule_cooldown_suppressed(&self, rule_id: &str) {
+        self.rule_stats(rule_id)
+            .cooldown_suppressions
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_gas_result(&self, success: bool) {
+        let counter = if success {
+            &self.gas_success
+        } else {
+            &self.gas_failure
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_discord_result(&self, success: bool) {
+        let counter = if success {
+            &self.discord_success
+        } else {
+            &self.discord_failure
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let rules = self
+            .rules
+            .read()
+            .iter()
+            .map(|(id, stats)| RuleStatsSnapshot {
+                rule_id: id.clone(),
+                evaluations: stats.evaluations.load(Ordering::Relaxed),
+                matches: stats.matches.load(Ordering::Relaxed),
+                cooldown_suppressions: stats.cooldown_suppressions.load(Ordering::Relaxed),
+            })
+            .collect();
+
+        MetricsSnapshot {
+            frames_captured: self.frames_captured.load(Ordering::Relaxed),
+            ocr_runs: self.ocr_runs.load(Ordering::Relaxed),
+            ocr_latency_ms: self.ocr_latency_ms.snapshot(),
+            findings_per_frame: self.findings_per_frame.snapshot(),
+            rules,
+            gas_success: self.gas_success.load(Ordering::Relaxed),
+            gas_failure: self.gas_failure.load(Ordering::Relaxed),
+            discord_success: self.discord_success.load(Ordering::Relaxed),
+            discord_failure: self.discord_failure.load(Ordering::Relaxed),
+        }
+    }
+
+    fn to_prometheus_text(&self) -> String {
+        let snap = self.snapshot();
+        let mut out = String::new();
+
+        out.push_str("# TYPE pyauto_frames_captured_total counter\n");
+        out.push_str(&format!(
+            "pyauto_frames_captured_total {}\n",
+            snap.frames_captured
+        ));
+
+        out.push_str("# TYPE pyauto_ocr_runs_total counter\n");
+        out.push_str(&format!("pyauto_ocr_runs_total {}\n", snap.ocr_runs));
+
+        write_histogram(&mut out, "pyauto_ocr_latency_ms", &snap.ocr_latency_ms);
+        write_histogram(
+            &mut out,
+            "pyauto_findings_per_frame",
+            &snap.findings_per_frame,
+        );
+
+        out.push_str("# TYPE pyauto_rule_evaluations_total counter\n");
+        for rule in &snap.rules {
+            out.push_str(&format!(
+                "pyauto_rule_evaluations_total{{rule_id=\"{}\"}} {}\n",
+                rule.rule_id, rule.evaluations
+            ));
+        }
+        out.push_str("# TYPE pyauto_rule_matches_total counter\n");
+        for rule in &snap.rules {
+            out.push_str(&format!(
+                "pyauto_rule_matches_total{{rule_id=\"{}\"}} {}\n",
+                rule.rule_id, rule.matches
+            ));
+        }
+        out.push_str("# TYPE pyauto_rule_cooldown_suppressions_total counter\n");
+        for rule in &snap.rules {
+            out.push_str(&format!(
+                "pyauto_rule_cooldown_suppressions_total{{rule_id=\"{}\"}} {}\n",
+                rule.rule_id, rule.cooldown_suppressions
+            ));
+        }
+
+        out.push_str("# TYPE pyauto_gas_uplink_total counter\n");
+        out.push_str(&format!(
+            "pyauto_gas_uplink_total{{result=\"success\"}} {}\n",
+            snap.gas_success
+        ));
+        out.push_str(&format!(
+            "pyauto_gas_uplink_total{{result=\"failure\"}} {}\n",
+            snap.gas_failure
+        ));
+
+        out.push_str("# TYPE pyauto_discord_notify_total counter\n");
+        out.push_str(&format!(
+            "pyauto_discord_notify_total{{result=\"success\"}} {}\n",
+            snap.discord_success
+        ));
+        out.push_str(&format!(
+            "pyauto_discord_notify_total{{result=\"failure\"}} {}\n",
+            snap.discord_failure
+        ));
+
+        out
+    }
+}
+
+fn write_histogram(out: &mut String, name: &str, hist: &HistogramSnapshot) {
+    out.push_str(&format!("# TYPE {} histogram\n", name));
+    for (bound, count) in &hist.buckets {
+        out.push_str(&format!("{}_bucket{{le=\"{}\"}} {}\n", name, bound, count));
+    }
+    out.push_str(&format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, hist.count));
+    out.push_str(&format!("{}_sum {}\n", name, hist.sum));
+    out.push_str(&format!("{}_count {}\n", name, hist.count));
+}
+
+/// Spawns a minimal single-endpoint HTTP server (no framework — one more
+/// dependency for one GET handler isn't worth it) that answers every
+/// request with the Prometheus text exposition of `metrics`. Bound to
+/// `127.0.0.1:port` since this is meant for a local scraper, not public
+/// exposure.
+pub fn serve_prometheus(metrics: Arc<Metrics>, port: u16) -> std::io::Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let _ = handle_metrics_request(stream, &metrics);
+                }
+                Err(_) => continue,
+            }
+        }
+    }))
+}
+
+fn handle_metrics_request(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    // Single endpoint, so the request itself (method/path/headers) is
+    // irrelevant — just drain it before writing the response.
+    let mut discard = [0u8; 1024];
+    let _ = stream.read(&mut discard);
+
+    let body = metrics.to_prometheus_text();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())
+}
@@ -1,7 +1,16 @@
+pub mod binary_export;
 pub mod capture;
+pub mod hardware_token;
+pub mod history;
 pub mod input;
 pub mod license;
+pub mod metrics;
 pub mod ocr;
+pub mod png_optimize;
+pub mod preprocess;
+pub mod rule_engine;
+pub mod scripting;
+pub mod stream;
 
 use parking_lot::RwLock;
 use std::sync::{
@@ -17,10 +26,8 @@ use self::capture::{crop_buffer, Region, ScreenCapturer};
 use self::input::InputController;
 use self::ocr::{OcrData, OcrEngine};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
-use image::{imageops, ExtendedColorType, ImageBuffer, ImageEncoder, Rgba};
-use regex::Regex;
+use image::ExtendedColorType;
 use serde::{Deserialize, Serialize};
-use strsim::normalized_levenshtein;
 use tauri::{AppHandle, Emitter};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
     VIRTUAL_KEY, VK_0, VK_1, VK_2, VK_3, VK_4, VK_5, VK_6, VK_7, VK_8, VK_9, VK_A, VK_B, VK_C,
@@ -52,6 +59,18 @@ pub struct Rule {
     pub min_value: Option<f32>,
     pub target_attribute: Option<String>,
     pub cooldown: f32,
+    /// Language to stem/filter stopwords against when matching
+    /// `trigger_text` (e.g. "en", "es"). Falls back to `AppConfig`'s
+    /// `default_language`, then English, when unset.
+    pub language: Option<String>,
+    /// Rhai script run on match in place of a plain `global_action_key`
+    /// press (see `scripting.rs` for the bound API). GAS/Discord/evidence
+    /// actions still run either way. Leave unset to keep the plain-keypress
+    /// shorthand.
+    pub script: Option<String>,
+    /// Hard wall-clock timeout for `script`, in milliseconds. Defaults to
+    /// 500ms if `script` is set and this isn't.
+    pub script_timeout_ms: Option<u64>,
 }
 
 // Global constants removed.
@@ -69,6 +88,33 @@ pub struct AppConfig {
     pub account_data: Option<String>,
     pub gas_url: Option<String>,
     pub api_secret: Option<String>,
+    /// Default language code (e.g. "en", "es") used to stem/filter
+    /// stopwords on `Rule::trigger_text` when a rule doesn't set its own.
+    pub default_language: Option<String>,
+    /// Master switch for the stemming/stopword pipeline. Defaults to
+    /// `false` (unset keeps the old exact lowercase/split Levenshtein-only
+    /// matching, consistent with every other additive option here); set to
+    /// `true` to turn normalization on.
+    pub normalize_text: Option<bool>,
+    /// Ordered image preprocessing stages run on the cropped ROI before
+    /// OCR. Unset keeps the legacy fixed histogram-based pass
+    /// (`legacy_preprocess`) so existing profiles don't change behavior.
+    pub preprocess_pipeline: Option<Vec<preprocess::PreprocessStage>>,
+    /// Local port to serve the Prometheus text-format metrics endpoint on
+    /// (see `metrics::serve_prometheus`). Unset disables it; the Tauri
+    /// `get_metrics_snapshot` command works either way.
+    pub metrics_port: Option<u16>,
+    /// Oxipng-style filter/deflate search breadth (0-6) used to minimize
+    /// saved evidence PNGs; see `png_optimize::optimize_png`. Unset
+    /// defaults to 3 (a handful of candidates, not an exhaustive search).
+    pub evidence_png_level: Option<u8>,
+    /// Target byte size for the streamed preview frame (see `stream.rs`).
+    /// Unset defaults to `stream::DEFAULT_BUDGET_BYTES` (~6 KB).
+    pub stream_budget_bytes: Option<u32>,
+    /// (scale, quality) candidates the Body thread picks the
+    /// best-fitting encoding from every streamed frame. Unset defaults to
+    /// `stream::default_candidates()`.
+    pub stream_candidates: Option<Vec<stream::StreamCandidate>>,
 }
 
 fn emit_log(app: &AppHandle, log_type: LogType, msg: String) {
@@ -84,14 +130,95 @@ pub struct RustBot {
     active: Arc<AtomicBool>,
     handle: Option<thread::JoinHandle<()>>,
     brain_handle: Option<thread::JoinHandle<()>>,
+    // Opened once for the app's lifetime (not per start/stop cycle) so
+    // sighting history survives across engine restarts and can be queried
+    // even while the bot is stopped. `None` if the index failed to open
+    // (permissions, a stale lock file, disk full, a corrupted index) —
+    // history search is then just unavailable rather than crashing the app.
+    history: Option<Arc<history::HistoryIndex>>,
+    history_handle: Option<thread::JoinHandle<()>>,
+    // Last raw capture handed to the Brain thread (full desktop frame, not
+    // the cropped ROI). Kept on the bot itself, rather than only as a local
+    // in `start`, so the preprocessing debug command can crop + run the
+    // pipeline against whatever the Brain last saw.
+    last_frame: Arc<RwLock<Option<(Vec<u8>, u32, u32)>>>,
+    // Opened once for the app's lifetime, same reasoning as `history`: a
+    // dashboard or scraper should be able to read counters whether or not
+    // the engine is currently running.
+    metrics: Arc<metrics::Metrics>,
+    metrics_server_handle: Option<thread::JoinHandle<()>>,
 }
 
 impl RustBot {
     pub fn new() -> Self {
+        let history = match history::HistoryIndex::open(std::path::Path::new("ocr_history_index"))
+        {
+            Ok(index) => Some(index),
+            Err(e) => {
+                println!(
+                    "Failed to open OCR history index; history search will be unavailable: {}",
+                    e
+                );
+                None
+            }
+        };
+        let history_handle = history.as_ref().map(|h| h.spawn_indexer());
+
         Self {
             active: Arc::new(AtomicBool::new(false)),
             handle: None,
             brain_handle: None,
+            history,
+            history_handle,
+            last_frame: Arc::new(RwLock::new(None)),
+            metrics: metrics::Metrics::new(),
+            metrics_server_handle: None,
+        }
+    }
+
+    /// Shared handle for Tauri commands to query sighting history,
+    /// independent of whether the engine is currently running. `None` if
+    /// the index failed to open at startup.
+    pub fn history(&self) -> Option<Arc<history::HistoryIndex>> {
+        self.history.clone()
+    }
+
+    /// Shared handle for Tauri commands to read a metrics snapshot
+    /// independent of whether the engine is currently running.
+    pub fn metrics(&self) -> Arc<metrics::Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Runs `pipeline` (or the legacy fixed pass, if empty) against the
+    /// cropped ROI of whatever frame the Brain thread last captured,
+    /// returning every intermediate stage for visual debugging. `None` if
+    /// no frame has been captured yet (engine never started, or too soon
+    /// after starting).
+    pub fn debug_preprocess(
+        &self,
+        pipeline: &[preprocess::PreprocessStage],
+    ) -> Option<Vec<preprocess::StageOutput>> {
+        let frame = self.last_frame.read().clone()?;
+        let (data, w, h) = frame;
+        let roi = brain_roi();
+        let cropped = capture::crop_buffer(&data, w, h, roi)?;
+
+        if pipeline.is_empty() {
+            let mut legacy = cropped;
+            legacy_preprocess(&mut legacy);
+            Some(vec![preprocess::StageOutput {
+                label: "legacy".to_string(),
+                data: legacy,
+                width: roi.width,
+                height: roi.height,
+            }])
+        } else {
+            Some(preprocess::run_pipeline(
+                &cropped,
+                roi.width,
+                roi.height,
+                pipeline,
+            ))
         }
     }
 
@@ -104,10 +231,32 @@ impl RustBot {
         let active_flag_brain = self.active.clone();
         let app_handle_brain = app_handle.clone();
         let brain_config = config.clone();
+        let stream_budget = config
+            .stream_budget_bytes
+            .map(|b| b as usize)
+            .unwrap_or(stream::DEFAULT_BUDGET_BYTES);
+        let stream_candidates = config
+            .stream_candidates
+            .clone()
+            .unwrap_or_else(stream::default_candidates);
+        let brain_history = self.history();
+        let brain_metrics = self.metrics();
+        let body_metrics = self.metrics();
+
+        if let Some(port) = config.metrics_port {
+            match metrics::serve_prometheus(self.metrics(), port) {
+                Ok(handle) => self.metrics_server_handle = Some(handle),
+                Err(e) => emit_log(
+                    &app_handle,
+                    LogType::System,
+                    format!("Failed to start metrics endpoint on port {}: {}", port, e),
+                ),
+            }
+        }
 
         // Shared Image Frame (Capture -> Brain)
         // (Data, Width, Height)
-        let latest_frame: Arc<RwLock<Option<(Vec<u8>, u32, u32)>>> = Arc::new(RwLock::new(None));
+        let latest_frame = self.last_frame.clone();
         let brain_frame = latest_frame.clone();
 
         emit_log(
@@ -148,7 +297,13 @@ impl RustBot {
             let mut controller = InputController::new();
 
             // ROI: Configured for "Auto-Buy" detection (Expanded Vertically)
-            let roi = Region::new(320, 0, 1280, 1080);
+            let roi = brain_roi();
+            let pipeline = brain_config.preprocess_pipeline.clone().unwrap_or_default();
+
+            // Compile the configured rules into condition trees once up
+            // front; re-evaluating the config on every finding was wasted
+            // work and made per-rule cooldown state awkward to track.
+            let compiled_rules = build_compiled_rules(&brain_config, brain_metrics.clone());
 
             // ANTI-AFK STATE
             let mut last_afk = Instant::now();
@@ -187,16 +342,30 @@ impl RustBot {
                 if let Some((_data, w, h)) = frame_opt {
                     if let Some(ref engine) = ocr {
                         // 1. CROPPING
-                        if let Some(mut cropped_data) = crop_buffer(&_data, w, h, roi) {
-                            // 3.5 PRE-PROCESSING (High Contrast Grayscale)
-                            preprocess_image(&mut cropped_data);
+                        if let Some(cropped_data) = crop_buffer(&_data, w, h, roi) {
+                            // 3.5 PRE-PROCESSING (configurable pipeline; see
+                            // preprocess.rs. Unset config keeps the legacy
+                            // fixed histogram-based pass.)
+                            let (cropped_data, ocr_w, ocr_h) = if pipeline.is_empty() {
+                                let mut legacy = cropped_data;
+                                legacy_preprocess(&mut legacy);
+                                (legacy, roi.width, roi.height)
+                            } else {
+                                let stages =
+                                    preprocess::run_pipeline(&cropped_data, roi.width, roi.height, &pipeline);
+                                let last = stages.into_iter().last().expect("non-empty pipeline");
+                                (last.data, last.width, last.height)
+                            };
 
                             // 4. RECOGNITION
-                            // let _start_ocr = Instant::now();
-                            match engine.process_frame(&cropped_data, roi.width, roi.height) {
+                            let start_ocr = Instant::now();
+                            match engine.process_frame(&cropped_data, ocr_w, ocr_h) {
                                 Ok(findings) => {
                                     // Vec<OcrData>
-                                    // let duration = start_ocr.elapsed();
+                                    brain_metrics.record_ocr(
+                                        start_ocr.elapsed().as_secs_f64() * 1000.0,
+                                        findings.len(),
+                                    );
 
                                     if !findings.is_empty() {
                                         // Emit OCR Data for Visual Debugging (Bounding Boxes)
@@ -210,262 +379,52 @@ impl RustBot {
                                     let attribute_text =
                                         attribute_item.map(|item| item.text.to_lowercase());
 
-                                    // 5. DYNAMIC LOGIC
-                                    if let Some(rules) = &brain_config.rules {
-                                        for rule in rules {
-                                            for item in findings.iter() {
-                                                let text = item.text.to_lowercase(); // Use item.text
-
-                                                // --- Z. ATTRIBUTE CHECK ---
-                                                if let Some(req_attr) = &rule.target_attribute {
-                                                    // If rule requires attribute, we must match the Topmost text
-                                                    if let Some(curr_attr) = &attribute_text {
-                                                        if !curr_attr
-                                                            .contains(&req_attr.to_lowercase())
-                                                        {
-                                                            continue;
-                                                        }
-                                                    } else {
-                                                        continue; // Attribute required but none found
-                                                    }
-                                                }
-
-                                                // --- A. KEYWORD MATCHING (FUZZY) ---
-                                                let keyword_match =
-                                                    rule.trigger_text.iter().any(|t| {
-                                                        let keyword = t.to_lowercase();
-
-                                                        if keyword.contains(' ') {
-                                                            // Phrase: Loose substring match (Classic)
-                                                            text.contains(&keyword)
-                                                        } else {
-                                                            // Word: Fuzzy Match (Levenshtein > 0.85)
-                                                            text.split(|c: char| {
-                                                                !c.is_alphanumeric()
-                                                            })
-                                                            .any(|word| {
-                                                                word == keyword
-                                                                    || normalized_levenshtein(
-                                                                        word, &keyword,
-                                                                    ) > 0.85
-                                                            })
-                                                        }
-                                                    });
-
-                                                if !keyword_match {
-                                                    continue;
-                                                }
-
-                                                // --- B. PRICE CHECK (REGEX) ---
-                                                let price_regex = Regex::new(r"[\d,\.]+").unwrap();
-                                                let mut matched_price: f32 = 0.0; // Capture for API
-
-                                                let price_satisfied = if rule.max_value.is_some()
-                                                    || rule.min_value.is_some()
-                                                {
-                                                    let mut found_valid_price = false;
-                                                    for cap in price_regex.find_iter(&item.text) {
-                                                        let num_str = cap.as_str().replace(',', "");
-                                                        if let Ok(val) = num_str.parse::<f32>() {
-                                                            let min_ok = rule
-                                                                .min_value
-                                                                .map_or(true, |min| val >= min);
-                                                            let max_ok = rule
-                                                                .max_value
-                                                                .map_or(true, |max| val <= max);
-                                                            if min_ok && max_ok {
-                                                                found_valid_price = true;
-                                                                matched_price = val; // Capture it
-                                                                break;
-                                                            }
-                                                        }
-                                                    }
-                                                    found_valid_price
-                                                } else {
-                                                    true
-                                                };
-
-                                                if !price_satisfied {
-                                                    continue;
-                                                }
-
-                                                // --- C. TRIGGER ACTION ---
-                                                emit_log(
-                                                    &app_handle_brain,
-                                                    LogType::Logic,
-                                                    format!(
-                                                        "Rule '{}' MATCHED. Text: '{}' Price: {}",
-                                                        rule.id, text, matched_price
-                                                    ),
-                                                );
-
-                                                // --- GAS INVENTORY UPLINK ---
-                                                // Fire and forget POST to GAS
-                                                // Name = The item that matched the rule (e.g., "Excalibur")
-                                                // Attribute = The topmost text (e.g., "Fire"), if distinct or present
-                                                let gas_name = item.text.clone();
-                                                let gas_attribute = attribute_item
-                                                    .map(|i| i.text.clone())
-                                                    .unwrap_or_else(|| "".to_string());
-
-                                                let gas_price = matched_price;
-                                                let gas_account = brain_config
-                                                    .account_data
-                                                    .clone()
-                                                    .unwrap_or("Unknown".to_string());
-
-                                                let gas_url = brain_config
-                                                    .gas_url
-                                                    .clone()
-                                                    .unwrap_or_default();
-                                                let api_secret = brain_config
-                                                    .api_secret
-                                                    .clone()
-                                                    .unwrap_or_default();
-
-                                                // EVIDENCE CAPTURE
-                                                // Save the cropped image to disk for the Merchant Bot to pick up
-                                                let evidence_dir =
-                                                    std::path::Path::new("captured_evidence");
-                                                if !evidence_dir.exists() {
-                                                    let _ = std::fs::create_dir(evidence_dir);
-                                                }
-                                                let timestamp = chrono::Local::now()
-                                                    .format("%Y%m%d_%H%M%S")
-                                                    .to_string();
-                                                let clean_name =
-                                                    gas_name.replace(" ", "_").replace(
-                                                        |c: char| !c.is_alphanumeric() && c != '_',
-                                                        "",
-                                                    );
-                                                let filename =
-                                                    format!("{}_{}.png", clean_name, timestamp);
-                                                let file_path = evidence_dir.join(&filename);
-
-                                                let evidence_w = roi.width;
-                                                let evidence_h = roi.height;
-                                                let evidence_data = cropped_data.clone();
-
-                                                // Spawn independent thread for IO and API call
-                                                thread::spawn(move || {
-                                                    // 1. Save Image Locally
-                                                    let abs_path_str =
-                                                        if let Some(img_buf) = ImageBuffer::<
-                                                            image::Rgb<u8>,
-                                                            Vec<u8>,
-                                                        >::from_raw(
-                                                            evidence_w,
-                                                            evidence_h,
-                                                            evidence_data,
-                                                        ) {
-                                                            match img_buf.save(&file_path) {
-                                                                Ok(_) => {
-                                                                    // Return absolute path
-                                                                    std::fs::canonicalize(
-                                                                        &file_path,
-                                                                    )
-                                                                    .map(|p| {
-                                                                        p.to_string_lossy()
-                                                                            .to_string()
-                                                                    })
-                                                                    .unwrap_or(filename.clone())
-                                                                }
-                                                                Err(e) => {
-                                                                    println!(
-                                                                        "Evidence Save Failed: {}",
-                                                                        e
-                                                                    );
-                                                                    "error".to_string()
-                                                                }
-                                                            }
-                                                        } else {
-                                                            "error_encoding".to_string()
-                                                        };
-
-                                                    // 2. GAS Payload with Image Path/URL
-                                                    let payload = serde_json::json!({
-                                                        "token": api_secret,
-                                                        "action": "restock",
-                                                        "name": gas_name,
-                                                        "attribute": gas_attribute,
-                                                        "data": gas_account,
-                                                        "price": gas_price,
-                                                        "image_url": abs_path_str
-                                                    });
-
-                                                    if !gas_url.is_empty() {
-                                                        match ureq::post(&gas_url)
-                                                            .send_json(payload)
-                                                        {
-                                                            Ok(_) => {}
-                                                            Err(e) => {
-                                                                println!(
-                                                                    "GAS Upload Failed: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    }
-                                                });
-
-                                                // Trigger Webhook (Async spawn)
-                                                if let Some(webhook_url) =
-                                                    &brain_config.discord_webhook_url
-                                                {
-                                                    if !webhook_url.is_empty() {
-                                                        let url = webhook_url.clone();
-                                                        let msg_text = format!(
-                                                            "**SNIPED!**\nItem: {}\nRule: {}",
-                                                            item.text, rule.id
-                                                        );
-                                                        let _sc = app_handle_brain.clone();
-                                                        thread::spawn(move || {
-                                                            let payload = serde_json::json!({
-                                                                "content": null,
-                                                                "embeds": [{
-                                                                    "title": "⚡ ITEM SECURED ⚡",
-                                                                    "description": msg_text,
-                                                                    "color": 5763719,
-                                                                    "footer": { "text": "Antigravity V4" }
-                                                                }],
-                                                                "username": "ANTIGRAVITY BOT",
-                                                                "avatar_url": "https://i.imgur.com/4M34hi2.png"
-                                                            });
-                                                            let _ =
-                                                                ureq::post(&url).send_json(payload);
-                                                        });
-                                                    }
-                                                }
-
-                                                // Parse Key
-                                                let key_str = brain_config
-                                                    .global_action_key
-                                                    .as_deref()
-                                                    .unwrap_or("e");
-                                                let vk = parse_key(key_str);
-                                                let duration =
-                                                    brain_config.hold_duration.unwrap_or(1.2);
-                                                let duration_ms = (duration * 1000.0) as u64;
-
-                                                emit_log(
-                                                    &app_handle_brain,
-                                                    LogType::Action,
-                                                    format!(
-                                                        "PRESS: '{}' ({}ms)",
-                                                        key_str, duration_ms
-                                                    ),
-                                                );
-                                                controller.long_press_key(vk, duration_ms);
-                                                emit_log(
-                                                    &app_handle_brain,
-                                                    LogType::Action,
-                                                    format!("RELEASED: '{}'", key_str),
-                                                );
-
-                                                thread::sleep(Duration::from_millis(1500));
-                                            }
+                                    // 4.6 HISTORY (off the hot path: queued, indexed in the
+                                    // background by history::HistoryIndex's own thread; a
+                                    // no-op if the index failed to open at startup)
+                                    if let Some(brain_history) = &brain_history {
+                                        let sighting_timestamp = chrono::Local::now().timestamp();
+                                        for finding in &findings {
+                                            brain_history.enqueue(history::Sighting {
+                                                text: finding.text.clone(),
+                                                matched_price: rule_engine::extract_price(
+                                                    &finding.text,
+                                                ),
+                                                attribute: attribute_text.clone(),
+                                                timestamp: sighting_timestamp,
+                                                evidence_path: None,
+                                                x: finding.x,
+                                                y: finding.y,
+                                                w: finding.w,
+                                                h: finding.h,
+                                            });
                                         }
                                     }
+
+                                    // 5. DYNAMIC LOGIC (pluggable rule engine, see rule_engine.rs)
+                                    let frame_ctx = rule_engine::FrameContext {
+                                        attribute_text: attribute_text.clone(),
+                                        frame_width: ocr_w,
+                                        frame_height: ocr_h,
+                                        timestamp: Instant::now(),
+                                    };
+                                    let evidence_data = cropped_data.clone();
+                                    let evidence_w = ocr_w;
+                                    let evidence_h = ocr_h;
+                                    rule_engine::evaluate_rules(
+                                        &compiled_rules,
+                                        &findings,
+                                        &frame_ctx,
+                                        &app_handle_brain,
+                                        &brain_metrics,
+                                        || {
+                                            vec![Box::new(rule_engine::SaveEvidence {
+                                                frame_data: evidence_data.clone(),
+                                                frame_w: evidence_w,
+                                                frame_h: evidence_h,
+                                            }) as Box<dyn rule_engine::Action>]
+                                        },
+                                    );
                                 }
                                 Err(e) => emit_log(
                                     &app_handle_brain,
@@ -504,6 +463,9 @@ impl RustBot {
             let mut loops: u64 = 0;
             let mut last_log = Instant::now();
             let target_frame_time = Duration::from_micros(22222); // ~45 FPS
+            // (width, height, quality, bytes) of the most recent adaptive
+            // streaming pick, surfaced in the heartbeat log.
+            let mut last_stream_choice: Option<(u32, u32, u8, usize)> = None;
 
             while active_flag.load(Ordering::SeqCst) {
                 let start = Instant::now();
@@ -511,6 +473,7 @@ impl RustBot {
                 // Capture
                 match capturer.capture_region(0, 0, 1920, 1080) {
                     Ok(pixels) => {
+                        body_metrics.record_frame_captured();
                         let w = 1920;
                         let h = 1080;
                         // Update Brain's view
@@ -520,60 +483,21 @@ impl RustBot {
                             }
                         }
 
-                        // Stream to Frontend (Extreme Optimization: Manual Subsampling)
-                        // Target: 480x270 (1/4th Scale) -> ~120KB Raw -> ~5KB JPEG
+                        // Stream to Frontend (Adaptive Bitrate: pick whichever
+                        // (scale, quality) candidate best fits the byte budget;
+                        // see stream.rs)
                         if loops % 2 == 0 {
-                            // Manual Downscale 4x + BGR->RGB Swap (Zero intermediate allocation)
-                            let target_w = 480;
-                            let target_h = 270;
-                            let mut small_buffer =
-                                Vec::with_capacity((target_w * target_h * 3) as usize);
-
-                            // Stride Calculation
-                            // Source width 1920
-                            // Skip 4 pixels horizontal, 4 pixels vertical
-
-                            for y in 0..target_h {
-                                let src_y = y * 4;
-                                let row_start = (src_y * 1920 * 4) as usize;
-                                for x in 0..target_w {
-                                    let src_x = x * 4;
-                                    let idx = row_start + (src_x * 4) as usize;
-
-                                    if idx + 2 < pixels.len() {
-                                        let b = pixels[idx];
-                                        let g = pixels[idx + 1];
-                                        let r = pixels[idx + 2];
-                                        // Push RGB
-                                        small_buffer.push(r);
-                                        small_buffer.push(g);
-                                        small_buffer.push(b);
-                                    } else {
-                                        // Padding if out of bounds (shouldn't happen with correct math)
-                                        small_buffer.push(0);
-                                        small_buffer.push(0);
-                                        small_buffer.push(0);
-                                    }
-                                }
-                            }
-
-                            // Encode small buffer as JPEG (Quality 50 is plenty for preview)
-                            let mut jpeg_buffer = Vec::new();
-                            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
-                                &mut jpeg_buffer,
-                                50,
-                            );
-
-                            // Use RgbImage to wrap our raw buffer
-                            if let Some(img) = ImageBuffer::<image::Rgb<u8>, Vec<u8>>::from_raw(
-                                target_w,
-                                target_h,
-                                small_buffer,
+                            if let Some(result) = stream::select_best_candidate(
+                                &pixels,
+                                w,
+                                h,
+                                &stream_candidates,
+                                stream_budget,
                             ) {
-                                if let Ok(_) = encoder.encode_image(&img) {
-                                    let b64 = BASE64.encode(&jpeg_buffer);
-                                    let _ = app_handle_stream.emit("frame-update", b64);
-                                }
+                                last_stream_choice =
+                                    Some((result.width, result.height, result.quality, result.data.len()));
+                                let b64 = BASE64.encode(&result.data);
+                                let _ = app_handle_stream.emit("frame-update", b64);
                             }
                         }
                     }
@@ -587,10 +511,16 @@ impl RustBot {
                 // Stats & Timing
                 loops += 1;
                 if last_log.elapsed() >= Duration::from_secs(5) {
+                    let stream_note = match last_stream_choice {
+                        Some((w, h, q, bytes)) => {
+                            format!(", stream: {}x{} q{} ({} bytes)", w, h, q, bytes)
+                        }
+                        None => String::new(),
+                    };
                     emit_log(
                         &app_handle_body,
                         LogType::System,
-                        format!("Heartbeat: {} FPS", loops / 5),
+                        format!("Heartbeat: {} FPS{}", loops / 5, stream_note),
                     );
                     loops = 0;
                     last_log = Instant::now();
@@ -620,6 +550,116 @@ impl RustBot {
     }
 }
 
+impl Drop for RustBot {
+    fn drop(&mut self) {
+        if let Some(history) = &self.history {
+            history.shutdown();
+        }
+        if let Some(h) = self.history_handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+/// Translates the legacy flat `Rule` config (trigger text + price bounds +
+/// optional attribute requirement) into a `rule_engine::CompiledRule`: an
+/// `And` of whichever conditions the rule actually specifies, plus the
+/// standard press/GAS/Discord action set. A rule with an explicit
+/// `condition` tree isn't supported from JSON yet, but `CompiledRule` is
+/// built to carry one the day a rule wants more than this gives it.
+fn build_compiled_rules(
+    config: &AppConfig,
+    metrics: Arc<metrics::Metrics>,
+) -> Vec<rule_engine::CompiledRule> {
+    let Some(rules) = &config.rules else {
+        return Vec::new();
+    };
+
+    rules
+        .iter()
+        .map(|rule| {
+            let mut conditions: Vec<Box<dyn rule_engine::Condition>> = Vec::new();
+
+            if let Some(req_attr) = &rule.target_attribute {
+                conditions.push(Box::new(rule_engine::AttributeContains {
+                    text: req_attr.to_lowercase(),
+                }));
+            }
+
+            let normalizer = if config.normalize_text.unwrap_or(false) {
+                let lang_code = rule
+                    .language
+                    .as_deref()
+                    .or(config.default_language.as_deref())
+                    .unwrap_or("en");
+                Some(Arc::new(ocr::normalize::Normalizer::new(
+                    ocr::normalize::Language::from_code(lang_code),
+                )))
+            } else {
+                None
+            };
+
+            conditions.push(Box::new(rule_engine::KeywordFuzzy {
+                keywords: rule.trigger_text.clone(),
+                normalizer,
+            }));
+
+            if rule.max_value.is_some() || rule.min_value.is_some() {
+                conditions.push(Box::new(rule_engine::PriceRange {
+                    min: rule.min_value,
+                    max: rule.max_value,
+                }));
+            }
+
+            let condition: Box<dyn rule_engine::Condition> = Box::new(rule_engine::And(conditions));
+
+            let key_str = config.global_action_key.as_deref().unwrap_or("e").to_string();
+            let hold_ms = (config.hold_duration.unwrap_or(1.2) * 1000.0) as u64;
+
+            // A rule script replaces the plain keypress; `global_action_key`
+            // stays the no-script shorthand when `rule.script` is unset.
+            let primary_action: Box<dyn rule_engine::Action> = match &rule.script {
+                Some(script) => Box::new(scripting::ScriptAction {
+                    script: script.clone(),
+                    timeout: Duration::from_millis(rule.script_timeout_ms.unwrap_or(500)),
+                }),
+                None => Box::new(rule_engine::PressKey {
+                    key: key_str,
+                    hold_ms,
+                }),
+            };
+
+            let actions: Vec<Box<dyn rule_engine::Action>> = vec![
+                primary_action,
+                Box::new(rule_engine::GasUplink {
+                    gas_url: config.gas_url.clone().unwrap_or_default(),
+                    api_secret: config.api_secret.clone().unwrap_or_default(),
+                    account_data: config.account_data.clone().unwrap_or("Unknown".to_string()),
+                    metrics: metrics.clone(),
+                }),
+                Box::new(rule_engine::DiscordNotify {
+                    webhook_url: config.discord_webhook_url.clone().unwrap_or_default(),
+                    metrics: metrics.clone(),
+                }),
+            ];
+
+            rule_engine::CompiledRule::new(
+                rule.id.clone(),
+                condition,
+                actions,
+                Duration::from_secs_f32(rule.cooldown.max(0.0)),
+            )
+        })
+        .collect()
+}
+
+/// Detection ROI ("Auto-Buy" region, expanded vertically), shared between
+/// the Brain loop and the preprocessing debug command so they crop the same
+/// rectangle out of whatever frame was captured.
+fn brain_roi() -> Region {
+    Region::new(320, 0, 1280, 1080)
+}
+
 fn parse_key(k: &str) -> VIRTUAL_KEY {
     match k.to_lowercase().as_str() {
         "a" => VK_A,
@@ -678,7 +718,7 @@ fn parse_key(k: &str) -> VIRTUAL_KEY {
     }
 }
 
-fn preprocess_image(data: &mut [u8]) {
+fn legacy_preprocess(data: &mut [u8]) {
     // Histogram-based Preprocessing (V2)
     // Solves "Hollow Text" issue (White text, Black outline, Light BG)
 
@@ -802,42 +842,71 @@ pub fn manual_ingest_logic(
     config: AppConfig,
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-    // 1. Save Evidence
+    // 1. Load Image
+    let img =
+        image::load_from_memory(&image_data).map_err(|e| format!("Bad image format: {}", e))?;
+    let mut rgba_img = img.to_rgba8();
+    let width = rgba_img.width();
+    let height = rgba_img.height();
+
+    // 2. Save Evidence (re-encoded as a minimized PNG rather than the raw
+    // uploaded bytes; see png_optimize.rs)
     let evidence_dir = std::path::Path::new("captured_evidence");
     if !evidence_dir.exists() {
         let _ = std::fs::create_dir(evidence_dir);
     }
     let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
     let safe_hint = filename_hint.replace(|c: char| !c.is_alphanumeric() && c != '.', "_");
-    let save_path = evidence_dir.join(format!("manual_{}_{}", timestamp, safe_hint));
-
-    std::fs::write(&save_path, &image_data)
+    let save_path = evidence_dir.join(format!("manual_{}_{}.png", timestamp, safe_hint));
+
+    let png_level = config.evidence_png_level.unwrap_or(3);
+    let optimized_png = png_optimize::optimize_png(
+        rgba_img.as_raw(),
+        width,
+        height,
+        ExtendedColorType::Rgba8,
+        png_level,
+    );
+    std::fs::write(&save_path, &optimized_png)
         .map_err(|e| format!("Failed to save evidence: {}", e))?;
 
     let abs_path = std::fs::canonicalize(&save_path)
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or(save_path.to_string_lossy().to_string());
 
-    // 2. Load and Preprocess Image
-    let img =
-        image::load_from_memory(&image_data).map_err(|e| format!("Bad image format: {}", e))?;
-    let mut rgba_img = img.to_rgba8();
-    let width = rgba_img.width();
-    let height = rgba_img.height();
-
+    // 3. Preprocess Image
     let raw_pixels = rgba_img.as_flat_samples_mut();
     let mut pixel_data = raw_pixels.as_slice().to_vec(); // Copy to vector
 
-    preprocess_image(&mut pixel_data);
+    legacy_preprocess(&mut pixel_data);
+
+    // 3.5. Archival 1-bit export of the binarized buffer, alongside the
+    // color original, for debugging OCR misreads (see binary_export.rs).
+    // Only written when the preprocessed buffer actually is two-tone;
+    // legacy_preprocess's auto-levels fallback isn't.
+    let gray: Vec<u8> = pixel_data.chunks_exact(4).map(|chunk| chunk[0]).collect();
+    if let Some(packed) = binary_export::pack_1bit(&gray, width, height) {
+        let binary_png =
+            png_optimize::optimize_png(&packed, width, height, ExtendedColorType::L1, png_level);
+        let binary_path =
+            evidence_dir.join(format!("manual_{}_{}_binary.png", timestamp, safe_hint));
+        if let Err(e) = std::fs::write(&binary_path, &binary_png) {
+            emit_log(
+                &app_handle,
+                LogType::System,
+                format!("Binary evidence save failed: {}", e),
+            );
+        }
+    }
 
-    // 3. Run OCR
+    // 4. Run OCR
     let ocr = OcrEngine::new().map_err(|e| format!("OCR Init Failed: {}", e))?;
     // Passed raw bytes, width, height.
     let ocr_results = ocr
         .process_frame(&pixel_data, width, height)
         .map_err(|e| format!("OCR Failed: {}", e))?;
 
-    // 4. Extract Data
+    // 5. Extract Data
     if ocr_results.is_empty() {
         return Err("No text detected in image".to_string());
     }
@@ -881,7 +950,7 @@ pub fn manual_ingest_logic(
         return Err("GAS URL is not configured.".to_string());
     }
 
-    // 5. Send to GAS
+    // 6. Send to GAS
     let payload = serde_json::json!({
         "token": api_secret,
         "action": "restock",
@@ -1,3 +1,5 @@
+pub mod normalize;
+
 use serde::Serialize;
 use windows::Foundation::Collections::IVectorView;
 use windows::Globalization::Language;
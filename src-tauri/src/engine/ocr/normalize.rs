@@ -0,0 +1,98 @@
+//! Language-aware text normalization for matching OCR output against rule
+//! keywords. The old pipeline only lowercased and split on
+//! non-alphanumeric characters before a Levenshtein comparison, so rule
+//! authors had to enumerate every inflection of a word by hand ("stock",
+//! "restocking", "stocked", ...). This adds a proper pipeline: Unicode
+//! tokenization, diacritic stripping, stopword filtering, and stemming, so
+//! those all collapse onto one stem before comparison.
+//!
+//! The pipeline is opt-in per rule via `Rule::language` / the config's
+//! `normalize_text` flag; with it disabled, matching falls back to the
+//! exact lowercase/split/Levenshtein behavior this replaces.
+
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::HashSet;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Languages with both a stopword list and a Snowball stemmer available.
+/// Unrecognized config values fall back to `English`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Spanish,
+    French,
+    German,
+}
+
+impl Language {
+    pub fn from_code(code: &str) -> Self {
+        match code.to_lowercase().as_str() {
+            "es" | "spanish" => Self::Spanish,
+            "fr" | "french" => Self::French,
+            "de" | "german" => Self::German,
+            _ => Self::English,
+        }
+    }
+
+    fn stemmer_algorithm(self) -> Algorithm {
+        match self {
+            Self::English => Algorithm::English,
+            Self::Spanish => Algorithm::Spanish,
+            Self::French => Algorithm::French,
+            Self::German => Algorithm::German,
+        }
+    }
+
+    fn stopwords(self) -> &'static [&'static str] {
+        match self {
+            Self::English => &[
+                "a", "an", "the", "is", "are", "of", "to", "in", "on", "for", "and", "or", "with",
+            ],
+            Self::Spanish => &["el", "la", "los", "las", "de", "y", "o", "en", "un", "una"],
+            Self::French => &["le", "la", "les", "de", "et", "ou", "en", "un", "une", "des"],
+            Self::German => &["der", "die", "das", "und", "oder", "in", "ein", "eine"],
+        }
+    }
+}
+
+/// Tokenizes, strips stopwords, and stems OCR/rule text so that surface
+/// variants of a word compare equal. Construct one per language and share
+/// it across rules that use that language.
+pub struct Normalizer {
+    stemmer: Stemmer,
+    stopwords: HashSet<&'static str>,
+}
+
+impl Normalizer {
+    pub fn new(language: Language) -> Self {
+        Self {
+            stemmer: Stemmer::create(language.stemmer_algorithm()),
+            stopwords: language.stopwords().iter().copied().collect(),
+        }
+    }
+
+    /// Unicode-segments `text` into word tokens, lowercases and strips
+    /// diacritics, drops stopwords, and stems what's left.
+    pub fn normalize_text(&self, text: &str) -> Vec<String> {
+        text.unicode_words()
+            .filter_map(|word| self.normalize_token(word))
+            .collect()
+    }
+
+    /// Normalizes a single token; returns `None` if it's a stopword (or
+    /// empty after stripping), meaning it contributes nothing to matching.
+    pub fn normalize_token(&self, token: &str) -> Option<String> {
+        let lowered = token.to_lowercase();
+        let stripped: String = lowered
+            .nfd()
+            .filter(|c| !unicode_normalization::char::is_combining_mark(*c))
+            .collect();
+
+        if stripped.is_empty() || self.stopwords.contains(stripped.as_str()) {
+            return None;
+        }
+
+        Some(self.stemmer.stem(&stripped).into_owned())
+    }
+}
@@ -0,0 +1,446 @@
+//! Pluggable, parallel rule engine.
+//!
+//! Replaces the single hardcoded attribute/keyword/price/trigger pipeline
+//! that used to live inline in the Brain thread loop. A `Rule` is now a
+//! tree of `Condition`s plus a list of `Action`s, so expressing something
+//! like "attribute contains Fire AND (price < 1000 OR text contains
+//! 'clearance')" is a matter of composing conditions rather than editing
+//! the loop. Adding a new condition or action is one trait impl.
+//!
+//! Rules are `Send + Sync` and evaluated in parallel across rules via
+//! rayon; each rule owns its own cooldown state so concurrent evaluation
+//! can't race two frames into double-firing the same rule.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use regex::Regex;
+use strsim::normalized_levenshtein;
+use tauri::AppHandle;
+
+use super::metrics::Metrics;
+use super::ocr::normalize::Normalizer;
+use super::ocr::OcrData;
+use super::{emit_log, LogType};
+
+/// Read-only context shared across all conditions evaluated for one frame:
+/// the topmost-detected text (the "attribute"/title line), the ROI
+/// dimensions, and when the frame was captured. Keeping this separate from
+/// `OcrData` is what lets conditions stay pure and independently testable.
+pub struct FrameContext {
+    pub attribute_text: Option<String>,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub timestamp: Instant,
+}
+
+/// A single predicate over one OCR finding plus the shared frame context.
+pub trait Condition: Send + Sync {
+    fn eval(&self, item: &OcrData, ctx: &FrameContext) -> bool;
+}
+
+/// Fuzzy/substring keyword match, mirroring the original inline logic:
+/// multi-word phrases are loose substring matches, single words use
+/// normalized Levenshtein > 0.85.
+///
+/// When `normalizer` is set, single-word keywords are instead compared
+/// after both the OCR text and the keyword are tokenized, stopword-filtered
+/// and stemmed (see `ocr::normalize`), so "stock"/"restocking"/"stocked"
+/// all match one trigger word. Leave `normalizer` as `None` to keep the
+/// exact legacy behavior.
+pub struct KeywordFuzzy {
+    pub keywords: Vec<String>,
+    pub normalizer: Option<Arc<Normalizer>>,
+}
+
+impl Condition for KeywordFuzzy {
+    fn eval(&self, item: &OcrData, _ctx: &FrameContext) -> bool {
+        let text = item.text.to_lowercase();
+
+        if let Some(normalizer) = &self.normalizer {
+            let text_stems: Vec<String> = normalizer.normalize_text(&item.text);
+            return self.keywords.iter().any(|t| {
+                let keyword = t.to_lowercase();
+                if keyword.contains(' ') {
+                    return text.contains(&keyword);
+                }
+                let Some(keyword_stem) = normalizer.normalize_token(&keyword) else {
+                    return false;
+                };
+                text_stems.iter().any(|stem| {
+                    *stem == keyword_stem || normalized_levenshtein(stem, &keyword_stem) > 0.85
+                })
+            });
+        }
+
+        self.keywords.iter().any(|t| {
+            let keyword = t.to_lowercase();
+            if keyword.contains(' ') {
+                text.contains(&keyword)
+            } else {
+                text.split(|c: char| !c.is_alphanumeric()).any(|word| {
+                    word == keyword || normalized_levenshtein(word, &keyword) > 0.85
+                })
+            }
+        })
+    }
+}
+
+/// Requires the frame's attribute line (topmost text) to contain `text`.
+pub struct AttributeContains {
+    pub text: String,
+}
+
+impl Condition for AttributeContains {
+    fn eval(&self, _item: &OcrData, ctx: &FrameContext) -> bool {
+        match &ctx.attribute_text {
+            Some(attr) => attr.contains(&self.text.to_lowercase()),
+            None => false,
+        }
+    }
+}
+
+/// Requires a numeric price-looking substring of `item.text` to fall
+/// within `[min, max]` (either bound optional, as before).
+pub struct PriceRange {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl Condition for PriceRange {
+    fn eval(&self, item: &OcrData, _ctx: &FrameContext) -> bool {
+        if self.min.is_none() && self.max.is_none() {
+            return true;
+        }
+        let price_regex = Regex::new(r"[\d,\.]+").unwrap();
+        for cap in price_regex.find_iter(&item.text) {
+            let num_str = cap.as_str().replace(',', "");
+            if let Ok(val) = num_str.parse::<f32>() {
+                let min_ok = self.min.map_or(true, |min| val >= min);
+                let max_ok = self.max.map_or(true, |max| val <= max);
+                if min_ok && max_ok {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Pulls the first number-looking substring out of OCR text (e.g. a price
+/// tag), stripping thousands separators. Shared by `PriceRange`'s match
+/// check, `GasUplink`'s payload, and the history indexer.
+pub fn extract_price(text: &str) -> Option<f32> {
+    let price_regex = Regex::new(r"[\d,\.]+").unwrap();
+    price_regex
+        .find_iter(text)
+        .find_map(|cap| cap.as_str().replace(',', "").parse::<f32>().ok())
+}
+
+/// Requires the finding's bounding box to fall inside a region of the
+/// frame, expressed as a fraction (0.0-1.0) of the ROI width/height.
+pub struct SpatialRegion {
+    pub x_frac: (f32, f32),
+    pub y_frac: (f32, f32),
+}
+
+impl Condition for SpatialRegion {
+    fn eval(&self, item: &OcrData, ctx: &FrameContext) -> bool {
+        if ctx.frame_width == 0 || ctx.frame_height == 0 {
+            return false;
+        }
+        let x_norm = item.x / ctx.frame_width as f32;
+        let y_norm = item.y / ctx.frame_height as f32;
+        (self.x_frac.0..=self.x_frac.1).contains(&x_norm)
+            && (self.y_frac.0..=self.y_frac.1).contains(&y_norm)
+    }
+}
+
+pub struct And(pub Vec<Box<dyn Condition>>);
+impl Condition for And {
+    fn eval(&self, item: &OcrData, ctx: &FrameContext) -> bool {
+        self.0.iter().all(|c| c.eval(item, ctx))
+    }
+}
+
+pub struct Or(pub Vec<Box<dyn Condition>>);
+impl Condition for Or {
+    fn eval(&self, item: &OcrData, ctx: &FrameContext) -> bool {
+        self.0.iter().any(|c| c.eval(item, ctx))
+    }
+}
+
+pub struct Not(pub Box<dyn Condition>);
+impl Condition for Not {
+    fn eval(&self, item: &OcrData, ctx: &FrameContext) -> bool {
+        !self.0.eval(item, ctx)
+    }
+}
+
+/// Everything an `Action` needs to carry out a match: the matched finding,
+/// the frame's attribute text, the owning rule's id, and the app handle for
+/// logging/events. Actions that need config (webhook URLs, GAS endpoint,
+/// the key to press) are constructed with that config baked in.
+pub struct ActionContext<'a> {
+    pub item: &'a OcrData,
+    pub attribute_text: Option<&'a str>,
+    pub rule_id: &'a str,
+    pub app_handle: &'a AppHandle,
+    /// Set by `SaveEvidence` once it has written the match's screenshot to
+    /// disk, so a later action in the same firing (namely `GasUplink`) can
+    /// report where it went. `RefCell` because actions only ever see a
+    /// shared `&ActionContext` and run one at a time, in order, on a
+    /// single thread.
+    pub evidence_path: RefCell<Option<String>>,
+}
+
+/// A side effect fired when a rule matches.
+pub trait Action: Send + Sync {
+    fn execute(&self, ctx: &ActionContext);
+}
+
+/// Presses (and releases) `key` via an `InputController`. Built fresh per
+/// firing since `InputController` isn't `Sync`; this mirrors how the Brain
+/// loop already owns one controller and presses on demand.
+pub struct PressKey {
+    pub key: String,
+    pub hold_ms: u64,
+}
+
+impl Action for PressKey {
+    fn execute(&self, ctx: &ActionContext) {
+        let vk = super::parse_key(&self.key);
+        let mut controller = super::input::InputController::new();
+        emit_log(
+            ctx.app_handle,
+            LogType::Action,
+            format!("PRESS: '{}' ({}ms)", self.key, self.hold_ms),
+        );
+        controller.long_press_key(vk, self.hold_ms);
+        emit_log(
+            ctx.app_handle,
+            LogType::Action,
+            format!("RELEASED: '{}'", self.key),
+        );
+    }
+}
+
+/// Fire-and-forget POST to the Google Apps Script inventory uplink.
+pub struct GasUplink {
+    pub gas_url: String,
+    pub api_secret: String,
+    pub account_data: String,
+    pub metrics: Arc<Metrics>,
+}
+
+impl Action for GasUplink {
+    fn execute(&self, ctx: &ActionContext) {
+        if self.gas_url.is_empty() {
+            return;
+        }
+        // The matching PriceRange condition already confirmed a number in
+        // range exists in the text; recover it here for the payload.
+        let price = extract_price(&ctx.item.text).unwrap_or(0.0);
+        // Populated by `SaveEvidence`, which runs earlier in the same
+        // firing's action chain (see `build_compiled_rules`/`evaluate_rules`).
+        let image_url = ctx.evidence_path.borrow().clone().unwrap_or_default();
+
+        let payload = serde_json::json!({
+            "token": self.api_secret,
+            "action": "restock",
+            "name": ctx.item.text,
+            "attribute": ctx.attribute_text.unwrap_or(""),
+            "data": self.account_data,
+            "price": price,
+            "image_url": image_url,
+        });
+        let url = self.gas_url.clone();
+        let metrics = self.metrics.clone();
+        std::thread::spawn(move || match ureq::post(&url).send_json(payload) {
+            Ok(_) => metrics.record_gas_result(true),
+            Err(e) => {
+                println!("GAS Upload Failed: {}", e);
+                metrics.record_gas_result(false);
+            }
+        });
+    }
+}
+
+/// Fire-and-forget Discord webhook notification.
+pub struct DiscordNotify {
+    pub webhook_url: String,
+    pub metrics: Arc<Metrics>,
+}
+
+impl Action for DiscordNotify {
+    fn execute(&self, ctx: &ActionContext) {
+        if self.webhook_url.is_empty() {
+            return;
+        }
+        let msg_text = format!("**SNIPED!**\nItem: {}\nRule: {}", ctx.item.text, ctx.rule_id);
+        let url = self.webhook_url.clone();
+        let metrics = self.metrics.clone();
+        std::thread::spawn(move || {
+            let payload = serde_json::json!({
+                "content": null,
+                "embeds": [{
+                    "title": "⚡ ITEM SECURED ⚡",
+                    "description": msg_text,
+                    "color": 5763719,
+                    "footer": { "text": "Antigravity V4" }
+                }],
+                "username": "ANTIGRAVITY BOT",
+                "avatar_url": "https://i.imgur.com/4M34hi2.png"
+            });
+            match ureq::post(&url).send_json(payload) {
+                Ok(_) => metrics.record_discord_result(true),
+                Err(_) => metrics.record_discord_result(false),
+            }
+        });
+    }
+}
+
+/// Saves the cropped evidence buffer (set by the caller before firing) to
+/// `captured_evidence/`.
+pub struct SaveEvidence {
+    pub frame_data: Vec<u8>,
+    pub frame_w: u32,
+    pub frame_h: u32,
+}
+
+impl Action for SaveEvidence {
+    fn execute(&self, ctx: &ActionContext) {
+        let evidence_dir = std::path::Path::new("captured_evidence");
+        if !evidence_dir.exists() {
+            let _ = std::fs::create_dir(evidence_dir);
+        }
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+        let clean_name = ctx
+            .item
+            .text
+            .replace(' ', "_")
+            .replace(|c: char| !c.is_alphanumeric() && c != '_', "");
+        let filename = format!("{}_{}.png", clean_name, timestamp);
+        let file_path = evidence_dir.join(&filename);
+
+        if let Some(img_buf) = image::ImageBuffer::<image::Rgb<u8>, Vec<u8>>::from_raw(
+            self.frame_w,
+            self.frame_h,
+            self.frame_data.clone(),
+        ) {
+            match img_buf.save(&file_path) {
+                Ok(()) => {
+                    // Absolute, so a downstream GAS consumer can resolve it
+                    // without assuming our cwd.
+                    let path = std::fs::canonicalize(&file_path).unwrap_or(file_path);
+                    *ctx.evidence_path.borrow_mut() = Some(path.display().to_string());
+                }
+                Err(e) => println!("Evidence Save Failed: {}", e),
+            }
+        }
+    }
+}
+
+/// A compiled rule: a condition tree, the actions to fire on match, and a
+/// per-rule cooldown tracked via the last-fired timestamp (millis since an
+/// arbitrary epoch, stored atomically so concurrent rule evaluation across
+/// rayon workers can't race two frames into double-firing the same rule).
+pub struct CompiledRule {
+    pub id: String,
+    pub condition: Box<dyn Condition>,
+    pub actions: Vec<Box<dyn Action>>,
+    pub cooldown: Duration,
+    epoch: Instant,
+    last_fired_ms: AtomicI64,
+}
+
+impl CompiledRule {
+    pub fn new(
+        id: String,
+        condition: Box<dyn Condition>,
+        actions: Vec<Box<dyn Action>>,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            id,
+            condition,
+            actions,
+            cooldown,
+            epoch: Instant::now(),
+            last_fired_ms: AtomicI64::new(i64::MIN),
+        }
+    }
+
+    fn try_claim_cooldown(&self, now: Instant) -> bool {
+        let now_ms = now.duration_since(self.epoch).as_millis() as i64;
+        let last = self.last_fired_ms.load(Ordering::SeqCst);
+        if last != i64::MIN && now_ms - last < self.cooldown.as_millis() as i64 {
+            return false;
+        }
+        self.last_fired_ms
+            .compare_exchange(last, now_ms, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+/// Evaluates every rule against every finding in parallel (one rayon task
+/// per rule), firing each rule's actions on the first match that survives
+/// its own cooldown. `extra_actions` is invoked only on an actual match, so
+/// callers can lazily build per-match actions (e.g. `SaveEvidence` sourced
+/// from the current frame buffer) without paying the cost every frame.
+///
+/// `metrics` records one evaluation per rule per frame, plus a match or a
+/// cooldown-suppression for whichever finding (if any) satisfied the
+/// rule's condition — the two are tracked separately so a rule that's
+/// matching but going nowhere (stuck in cooldown) is distinguishable from
+/// one that's simply never matching.
+pub fn evaluate_rules<F>(
+    rules: &[CompiledRule],
+    findings: &[OcrData],
+    ctx: &FrameContext,
+    app_handle: &AppHandle,
+    metrics: &Metrics,
+    extra_actions: F,
+) where
+    F: Fn() -> Vec<Box<dyn Action>> + Sync,
+{
+    rules.par_iter().for_each(|rule| {
+        metrics.record_rule_eval(&rule.id);
+
+        for item in findings {
+            if !rule.condition.eval(item, ctx) {
+                continue;
+            }
+            if !rule.try_claim_cooldown(ctx.timestamp) {
+                metrics.record_rule_cooldown_suppressed(&rule.id);
+                continue;
+            }
+
+            metrics.record_rule_match(&rule.id);
+
+            emit_log(
+                app_handle,
+                LogType::Logic,
+                format!("Rule '{}' MATCHED. Text: '{}'", rule.id, item.text),
+            );
+
+            let action_ctx = ActionContext {
+                item,
+                attribute_text: ctx.attribute_text.as_deref(),
+                rule_id: &rule.id,
+                app_handle,
+                evidence_path: RefCell::new(None),
+            };
+            // `extra_actions` (SaveEvidence) runs first so its path lands
+            // in `action_ctx.evidence_path` before `GasUplink` reads it
+            // back out for its payload.
+            for action in extra_actions().iter().chain(rule.actions.iter()) {
+                action.execute(&action_ctx);
+            }
+            break;
+        }
+    });
+}
@@ -0,0 +1,126 @@
+//! Rhai scripting hook for rule actions.
+//!
+//! A match used to only be able to fire a single `global_action_key` press
+//! plus the built-in GAS/Discord/evidence side effects. This lets a `Rule`
+//! instead carry a script string that runs on match, bound to a small,
+//! deliberately narrow API: `press(key)`, `hold(key, ms)`, `click(x, y)`,
+//! `sleep(ms)`, `log(msg)`, and the match context as `matched_price`,
+//! `item_text`, `attribute`. Nothing else is in scope — no `import`, no
+//! file or network functions are registered on the engine, so there's
+//! nothing for a script to reach outside the sandbox with. A hard
+//! wall-clock timeout is enforced via Rhai's progress callback so a
+//! runaway script (an infinite loop, say) can't stall the Brain thread.
+//!
+//! `global_action_key` keeps working unscripted: `build_compiled_rules`
+//! only swaps in a `ScriptAction` in place of `PressKey` when `Rule::script`
+//! is set.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use rhai::{Dynamic, Engine, Scope};
+
+use super::input::InputController;
+use super::rule_engine::{extract_price, Action, ActionContext};
+use super::{emit_log, LogType};
+
+/// Instruction-count ceiling as a backstop against pathological scripts
+/// that manage to burn CPU faster than the progress callback's timeout
+/// check fires.
+const MAX_OPERATIONS: u64 = 2_000_000;
+
+/// Runs a user-authored Rhai script on match instead of the default
+/// `press(global_action_key)`. The built-in GAS/Discord/evidence actions
+/// are unaffected; only the press step is scriptable.
+pub struct ScriptAction {
+    pub script: String,
+    pub timeout: Duration,
+}
+
+impl Action for ScriptAction {
+    fn execute(&self, ctx: &ActionContext) {
+        if let Err(e) = run_script(&self.script, self.timeout, ctx) {
+            emit_log(
+                ctx.app_handle,
+                LogType::Action,
+                format!("Script error (rule '{}'): {}", ctx.rule_id, e),
+            );
+        }
+    }
+}
+
+fn run_script(
+    script: &str,
+    timeout: Duration,
+    ctx: &ActionContext,
+) -> Result<(), Box<rhai::EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let start = Instant::now();
+    engine.on_progress(move |_ops| {
+        if start.elapsed() > timeout {
+            Some(Dynamic::from("script exceeded its timeout"))
+        } else {
+            None
+        }
+    });
+
+    // `on_progress` only interrupts between bytecode ops, so it can't
+    // preempt a thread already blocked inside `std::thread::sleep` — a
+    // script calling `sleep`/`hold` with a large duration would otherwise
+    // stall the Brain thread well past `timeout`. Every blocking native
+    // function below clamps its own wait against the time actually left
+    // in the budget instead of relying solely on the progress callback.
+    let remaining_ms = move || timeout.saturating_sub(start.elapsed()).as_millis() as u64;
+
+    // Fresh per invocation, same as `PressKey` builds its own
+    // `InputController` per firing — it isn't `Sync`, and a script already
+    // runs synchronously on whichever thread fired the rule.
+    let controller = Rc::new(RefCell::new(InputController::new()));
+
+    {
+        let controller = controller.clone();
+        engine.register_fn("press", move |key: &str| {
+            if remaining_ms() == 0 {
+                return;
+            }
+            controller.borrow_mut().press_key(super::parse_key(key));
+        });
+    }
+    {
+        let controller = controller.clone();
+        engine.register_fn("hold", move |key: &str, ms: i64| {
+            let ms = (ms.max(0) as u64).min(remaining_ms());
+            controller
+                .borrow_mut()
+                .long_press_key(super::parse_key(key), ms);
+        });
+    }
+    {
+        let controller = controller.clone();
+        engine.register_fn("click", move |x: i64, y: i64| {
+            if remaining_ms() == 0 {
+                return;
+            }
+            controller.borrow_mut().click_at(x as i32, y as i32);
+        });
+    }
+    engine.register_fn("sleep", move |ms: i64| {
+        let ms = (ms.max(0) as u64).min(remaining_ms());
+        std::thread::sleep(Duration::from_millis(ms));
+    });
+
+    let app_handle = ctx.app_handle.clone();
+    engine.register_fn("log", move |msg: &str| {
+        emit_log(&app_handle, LogType::Action, format!("[script] {}", msg));
+    });
+
+    let mut scope = Scope::new();
+    scope.push("matched_price", extract_price(&ctx.item.text).unwrap_or(0.0) as f64);
+    scope.push("item_text", ctx.item.text.clone());
+    scope.push("attribute", ctx.attribute_text.unwrap_or("").to_string());
+
+    engine.run_with_scope(&mut scope, script)
+}
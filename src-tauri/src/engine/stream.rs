@@ -0,0 +1,151 @@
+//! Adaptive-bitrate preview streaming for the Body thread.
+//!
+//! The Body thread used to hard-code a 480x270 subsample at JPEG quality 50
+//! for every streamed frame, which looks fine on a fast link and stutters
+//! or lags behind on a slow one. This borrows oxipng's own parallel
+//! candidate-evaluation pattern (try several encodings at once, keep
+//! whichever fits): a small (scale, quality) grid is encoded concurrently
+//! via rayon, and the candidate closest to `budget_bytes` from below wins.
+//! The winner's length and bytes are compared and swapped together under
+//! one lock per tracked winner, so two racing threads can never leave the
+//! reported size and the stored bytes pointing at different candidates.
+
+use image::{imageops, ImageBuffer, Rgb};
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use serde::Deserialize;
+
+/// One (resolution, quality) point in the search grid.
+#[derive(Clone, Copy, Deserialize, Debug)]
+pub struct StreamCandidate {
+    pub width: u32,
+    pub height: u32,
+    pub quality: u8,
+}
+
+/// The default target byte budget (`AppConfig::stream_budget_bytes`),
+/// chosen to keep the preview smooth on slow links.
+pub const DEFAULT_BUDGET_BYTES: usize = 6 * 1024;
+
+/// The default candidate grid (`AppConfig::stream_candidates`): two scales
+/// times three quality levels.
+pub fn default_candidates() -> Vec<StreamCandidate> {
+    let mut candidates = Vec::new();
+    for &(width, height) in &[(480, 270), (360, 203)] {
+        for &quality in &[30u8, 50, 70] {
+            candidates.push(StreamCandidate { width, height, quality });
+        }
+    }
+    candidates
+}
+
+/// A winning candidate's encoded JPEG plus the parameters that produced it,
+/// so the caller can log what the adaptation picked.
+pub struct StreamResult {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub quality: u8,
+}
+
+/// Encodes every candidate in `candidates` against `pixels` (a BGRA
+/// `src_w`x`src_h` buffer) concurrently, and returns the one whose encoded
+/// size is largest among those still `<= budget_bytes` (i.e. the best
+/// quality that still fits). Falls back to the smallest candidate overall
+/// if none of them fit the budget. `None` only if every candidate fails to
+/// encode.
+pub fn select_best_candidate(
+    pixels: &[u8],
+    src_w: u32,
+    src_h: u32,
+    candidates: &[StreamCandidate],
+    budget_bytes: usize,
+) -> Option<StreamResult> {
+    let rgb_full = bgra_to_rgb(pixels, src_w, src_h)?;
+
+    // Length and bytes are compared and swapped together under a single
+    // lock per tracked winner, so one thread can never observe the other's
+    // length update without its matching data (which an `AtomicU64` length
+    // alongside a separately-locked `Mutex<Option<StreamResult>>` would
+    // allow: both threads can "win" the length compare-exchange in order
+    // but then race to write the mutex, leaving it holding stale bytes).
+    let best: Mutex<(u64, Option<StreamResult>)> = Mutex::new((0, None));
+    let smallest: Mutex<(u64, Option<StreamResult>)> = Mutex::new((u64::MAX, None));
+
+    candidates.par_iter().for_each(|candidate| {
+        let Some(data) = encode_candidate(&rgb_full, candidate) else {
+            return;
+        };
+        let len = data.len() as u64;
+
+        // Track the smallest-overall result as a fallback for when nothing
+        // fits the budget (e.g. a very busy frame under a tight budget).
+        {
+            let mut smallest = smallest.lock();
+            if len < smallest.0 {
+                *smallest = (
+                    len,
+                    Some(StreamResult {
+                        data: data.clone(),
+                        width: candidate.width,
+                        height: candidate.height,
+                        quality: candidate.quality,
+                    }),
+                );
+            }
+        }
+
+        if len as usize > budget_bytes {
+            return;
+        }
+
+        // Among candidates that fit, keep the largest (i.e. the one using
+        // the most of the budget, which is also the best-looking one).
+        let mut best = best.lock();
+        if len > best.0 {
+            *best = (
+                len,
+                Some(StreamResult {
+                    data,
+                    width: candidate.width,
+                    height: candidate.height,
+                    quality: candidate.quality,
+                }),
+            );
+        }
+    });
+
+    best.into_inner().1.or_else(|| smallest.into_inner().1)
+}
+
+fn bgra_to_rgb(pixels: &[u8], width: u32, height: u32) -> Option<ImageBuffer<Rgb<u8>, Vec<u8>>> {
+    let mut rgb = Vec::with_capacity(pixels.len() / 4 * 3);
+    for chunk in pixels.chunks_exact(4) {
+        rgb.push(chunk[2]);
+        rgb.push(chunk[1]);
+        rgb.push(chunk[0]);
+    }
+    ImageBuffer::from_raw(width, height, rgb)
+}
+
+fn encode_candidate(
+    rgb_full: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    candidate: &StreamCandidate,
+) -> Option<Vec<u8>> {
+    let resized = if (candidate.width, candidate.height) == rgb_full.dimensions() {
+        rgb_full.clone()
+    } else {
+        imageops::resize(
+            rgb_full,
+            candidate.width,
+            candidate.height,
+            imageops::FilterType::Triangle,
+        )
+    };
+
+    let mut jpeg_buffer = Vec::new();
+    let encoder =
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_buffer, candidate.quality);
+    encoder.encode_image(&resized).ok()?;
+    Some(jpeg_buffer)
+}
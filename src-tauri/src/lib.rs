@@ -57,6 +57,13 @@ async fn start_rust_engine(
             account_data: None,
             gas_url: None,
             api_secret: None,
+            default_language: None,
+            normalize_text: None,
+            preprocess_pipeline: None,
+            metrics_port: None,
+            evidence_png_level: None,
+            stream_budget_bytes: None,
+            stream_candidates: None,
         });
 
     let mut bot = state.rust_bot.lock().await;
@@ -224,11 +231,70 @@ fn verify_activation_key(key: String) -> bool {
     license::verify_signature(&hwid, &key)
 }
 
+#[tauri::command]
+fn activate_license(signature: String) -> Result<(), String> {
+    license::LicenseStore::activate(&signature)
+}
+
+#[tauri::command]
+fn is_license_activated() -> bool {
+    license::LicenseStore::is_activated()
+}
+
 #[tauri::command]
 fn generate_admin_keys() -> (String, String) {
     license::data_generate_admin_keys()
 }
 
+#[tauri::command]
+async fn query_ocr_history(
+    state: State<'_, AppState>,
+    query: String,
+    min_price: Option<f32>,
+    max_price: Option<f32>,
+    since: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<engine::history::HistoryHit>, String> {
+    let Some(history) = state.rust_bot.lock().await.history() else {
+        return Err("OCR history index is unavailable".to_string());
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        history.query(&engine::history::HistoryQuery {
+            text: query,
+            min_price,
+            max_price,
+            since,
+            limit: limit.unwrap_or(50),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_metrics_snapshot(
+    state: State<'_, AppState>,
+) -> Result<engine::metrics::MetricsSnapshot, String> {
+    Ok(state.rust_bot.lock().await.metrics().snapshot())
+}
+
+#[tauri::command]
+async fn debug_preprocess_pipeline(
+    state: State<'_, AppState>,
+    pipeline: Vec<engine::preprocess::PreprocessStage>,
+) -> Result<Vec<engine::preprocess::DebugStageImage>, String> {
+    let bot = state.rust_bot.lock().await;
+    let stages = bot
+        .debug_preprocess(&pipeline)
+        .ok_or_else(|| "No frame captured yet; start the engine first.".to_string())?;
+
+    Ok(stages
+        .iter()
+        .filter_map(|stage| stage.to_debug_image())
+        .collect())
+}
+
 #[tauri::command]
 async fn manual_ingest(
     app: tauri::AppHandle,
@@ -251,6 +317,13 @@ async fn manual_ingest(
             account_data: None,
             gas_url: None,
             api_secret: None,
+            default_language: None,
+            normalize_text: None,
+            preprocess_pipeline: None,
+            metrics_port: None,
+            evidence_png_level: None,
+            stream_budget_bytes: None,
+            stream_candidates: None,
         });
 
     // Run logic on thread pool to avoid blocking async runtime
@@ -271,6 +344,16 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
+        .setup(|_app| {
+            // Silent re-validation: if a signature was persisted from a prior
+            // activation, recompute the HWID and check it without prompting.
+            if license::LicenseStore::is_activated() {
+                println!("[License] Activated for this machine.");
+            } else {
+                println!("[License] Not activated.");
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             start_automation,
             stop_automation,
@@ -281,8 +364,13 @@ pub fn run() {
             stop_rust_engine,
             get_machine_id,
             verify_activation_key,
+            activate_license,
+            is_license_activated,
             generate_admin_keys,
-            manual_ingest
+            manual_ingest,
+            query_ocr_history,
+            debug_preprocess_pipeline,
+            get_metrics_snapshot
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");